@@ -0,0 +1,322 @@
+use std::time::Duration;
+
+use super::{
+    game_loop::MoveActionType,
+    held_key::{HeldKey, DEFAULT_KEY_HOLD},
+};
+
+pub(crate) const DEFAULT_DAS: Duration = Duration::from_millis(167);
+pub(crate) const DEFAULT_ARR: Duration = Duration::from_millis(33);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum ShiftDirection {
+    Left,
+    Right,
+}
+
+impl From<ShiftDirection> for MoveActionType {
+    fn from(value: ShiftDirection) -> Self {
+        match value {
+            ShiftDirection::Left => MoveActionType::MoveLeft,
+            ShiftDirection::Right => MoveActionType::MoveRight,
+        }
+    }
+}
+
+// Delayed auto shift, pressing a direction moves the piece once (the caller
+// does that so it stays in order with other inputs). Holding it for the DAS
+// delay starts moving it again every ARR interval, an ARR of zero
+// moves it all the way to the wall. When both directions are held the last
+// one pressed wins.
+pub(crate) struct AutoShift {
+    das: Duration,
+    arr: Duration,
+
+    left: HeldKey,
+    right: HeldKey,
+    direction: Option<ShiftDirection>,
+    held_for: Duration,
+    repeats: u32,
+}
+
+impl AutoShift {
+    pub(crate) fn new(das: Duration, arr: Duration) -> Self {
+        Self {
+            das,
+            arr,
+
+            left: HeldKey::new(DEFAULT_KEY_HOLD),
+            right: HeldKey::new(DEFAULT_KEY_HOLD),
+            direction: None,
+            held_for: Duration::ZERO,
+            repeats: 0,
+        }
+    }
+
+    pub(crate) fn with_key_hold(mut self, hold: Duration) -> Self {
+        self.left = HeldKey::new(hold);
+        self.right = HeldKey::new(hold);
+        self
+    }
+
+    pub(crate) fn set_timing(&mut self, das: Duration, arr: Duration) {
+        self.das = das;
+        self.arr = arr;
+    }
+
+    pub(crate) fn direction(&self) -> Option<ShiftDirection> {
+        self.direction
+    }
+
+    // Returns true for a new press, which should move the piece once.
+    pub(crate) fn press(&mut self, direction: ShiftDirection) -> bool {
+        // Terminals keep sending presses while a key is held down, those
+        // must not restart the delay.
+        if !self.key_mut(direction).press() && self.direction == Some(direction) {
+            return false;
+        }
+
+        self.start(direction);
+        true
+    }
+
+    pub(crate) fn release(&mut self, direction: ShiftDirection) {
+        self.key_mut(direction).release();
+        self.let_go(direction);
+    }
+
+    pub(crate) fn release_all(&mut self) {
+        self.left.reset();
+        self.right.reset();
+        self.direction = None;
+    }
+
+    // How many columns to move the piece this tick, `usize::MAX` means all
+    // the way to the wall.
+    pub(crate) fn tick(&mut self, dt: Duration) -> usize {
+        self.left.tick(dt);
+        self.right.tick(dt);
+        for direction in [ShiftDirection::Left, ShiftDirection::Right] {
+            if !self.key(direction).is_held() {
+                self.let_go(direction);
+            }
+        }
+        let Some(direction) = self.direction else {
+            return 0;
+        };
+
+        // A tap must not slide the piece, so the delay runs out no further
+        // than its end until the key is known to be held down.
+        self.held_for += dt;
+        if !self.key(direction).is_held_down() {
+            self.held_for = self.held_for.min(self.das);
+            return 0;
+        }
+        if self.held_for < self.das {
+            return 0;
+        }
+        if self.arr.is_zero() {
+            return usize::MAX;
+        }
+
+        let charged = self.held_for - self.das;
+        let repeats = (charged.as_nanos() / self.arr.as_nanos()) as u32 + 1;
+        let shifts = repeats - self.repeats;
+        self.repeats = repeats;
+        shifts as usize
+    }
+
+    fn key(&self, direction: ShiftDirection) -> &HeldKey {
+        match direction {
+            ShiftDirection::Left => &self.left,
+            ShiftDirection::Right => &self.right,
+        }
+    }
+
+    fn key_mut(&mut self, direction: ShiftDirection) -> &mut HeldKey {
+        match direction {
+            ShiftDirection::Left => &mut self.left,
+            ShiftDirection::Right => &mut self.right,
+        }
+    }
+
+    fn let_go(&mut self, direction: ShiftDirection) {
+        if self.direction != Some(direction) {
+            return;
+        }
+
+        // Fall back to the other direction if it is still held, it has to
+        // wait out the delay again before it repeats.
+        match (self.left.is_held(), self.right.is_held()) {
+            (true, _) => self.start(ShiftDirection::Left),
+            (_, true) => self.start(ShiftDirection::Right),
+            _ => self.direction = None,
+        }
+    }
+
+    fn start(&mut self, direction: ShiftDirection) {
+        self.direction = Some(direction);
+        self.held_for = Duration::ZERO;
+        self.repeats = 0;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use super::{AutoShift, ShiftDirection};
+    use crate::core::held_key::DEFAULT_KEY_HOLD;
+
+    fn millis(value: u64) -> Duration {
+        Duration::from_millis(value)
+    }
+
+    // Presses a direction and has the terminal repeat it straight away, so
+    // it is known to be held down.
+    fn hold(auto_shift: &mut AutoShift, direction: ShiftDirection) {
+        auto_shift.press(direction);
+        auto_shift.press(direction);
+    }
+
+    // Ticks in 10ms steps and returns the number of shifts for each tick.
+    fn run(auto_shift: &mut AutoShift, ticks: usize) -> Vec<usize> {
+        (0..ticks).map(|_| auto_shift.tick(millis(10))).collect()
+    }
+
+    #[test]
+    fn press_moves_once_straight_away() {
+        let mut under_test = AutoShift::new(millis(100), millis(20));
+
+        assert!(under_test.press(ShiftDirection::Left));
+        assert_eq!(0, under_test.tick(millis(10)));
+    }
+
+    #[test]
+    fn holding_repeats_after_the_delay() {
+        let mut under_test = AutoShift::new(millis(50), millis(20));
+        hold(&mut under_test, ShiftDirection::Right);
+
+        let shifts = run(&mut under_test, 10);
+
+        assert_eq!(vec![0, 0, 0, 0, 1, 0, 1, 0, 1, 0], shifts);
+    }
+
+    #[test]
+    fn repeats_catch_up_on_long_ticks() {
+        let mut under_test = AutoShift::new(millis(50), millis(10));
+        hold(&mut under_test, ShiftDirection::Right);
+
+        assert_eq!(0, under_test.tick(millis(40)));
+        assert_eq!(4, under_test.tick(millis(40)));
+    }
+
+    #[test]
+    fn zero_arr_moves_to_the_wall() {
+        let mut under_test = AutoShift::new(millis(50), Duration::ZERO);
+        hold(&mut under_test, ShiftDirection::Left);
+
+        assert_eq!(0, under_test.tick(millis(40)));
+        assert_eq!(usize::MAX, under_test.tick(millis(10)));
+    }
+
+    #[test]
+    fn terminal_repeats_do_not_restart_the_delay() {
+        let mut under_test = AutoShift::new(millis(50), millis(20));
+        under_test.press(ShiftDirection::Left);
+
+        under_test.tick(millis(30));
+        assert!(!under_test.press(ShiftDirection::Left));
+
+        assert_eq!(1, under_test.tick(millis(20)));
+    }
+
+    #[test]
+    fn a_tap_without_a_release_never_repeats() {
+        let mut under_test = AutoShift::new(millis(50), millis(20));
+        under_test.press(ShiftDirection::Left);
+
+        let ticks = (DEFAULT_KEY_HOLD.as_millis() / 10) as usize;
+        assert_eq!(vec![0; ticks], run(&mut under_test, ticks));
+        assert_eq!(None, under_test.direction());
+    }
+
+    #[test]
+    fn a_late_terminal_repeat_starts_repeating_without_catching_up() {
+        let mut under_test = AutoShift::new(millis(50), millis(20));
+        under_test.press(ShiftDirection::Left);
+        run(&mut under_test, 40);
+
+        assert!(!under_test.press(ShiftDirection::Left));
+
+        assert_eq!(1, under_test.tick(millis(10)));
+    }
+
+    #[test]
+    fn repeating_stops_once_the_terminal_stops_repeating() {
+        let mut under_test = AutoShift::new(millis(50), millis(20));
+        hold(&mut under_test, ShiftDirection::Right);
+
+        run(&mut under_test, 20);
+
+        assert_eq!(None, under_test.direction());
+    }
+
+    #[test]
+    fn a_press_lasts_until_its_release_once_releases_are_reported() {
+        let mut under_test = AutoShift::new(millis(50), millis(20));
+        under_test.press(ShiftDirection::Left);
+        under_test.release(ShiftDirection::Left);
+        under_test.press(ShiftDirection::Left);
+
+        let shifts = run(&mut under_test, 100);
+
+        assert_eq!(Some(ShiftDirection::Left), under_test.direction());
+        assert_eq!(48, shifts.iter().sum::<usize>());
+    }
+
+    #[test]
+    fn release_stops_moving() {
+        let mut under_test = AutoShift::new(millis(50), millis(20));
+        under_test.press(ShiftDirection::Left);
+
+        under_test.release(ShiftDirection::Left);
+
+        assert_eq!(None, under_test.direction());
+        assert_eq!(vec![0; 10], run(&mut under_test, 10));
+    }
+
+    #[test]
+    fn last_pressed_direction_wins() {
+        let mut under_test = AutoShift::new(millis(50), millis(20));
+        under_test.press(ShiftDirection::Left);
+
+        assert!(under_test.press(ShiftDirection::Right));
+
+        assert_eq!(Some(ShiftDirection::Right), under_test.direction());
+        assert_eq!(0, under_test.tick(millis(10)));
+    }
+
+    #[test]
+    fn releasing_returns_to_the_direction_still_held() {
+        let mut under_test = AutoShift::new(millis(50), millis(20));
+        hold(&mut under_test, ShiftDirection::Left);
+        under_test.press(ShiftDirection::Right);
+
+        under_test.release(ShiftDirection::Right);
+
+        assert_eq!(Some(ShiftDirection::Left), under_test.direction());
+        assert_eq!(0, under_test.tick(millis(10)));
+        assert_eq!(1, under_test.tick(millis(40)));
+    }
+
+    #[test]
+    fn releasing_the_other_direction_changes_nothing() {
+        let mut under_test = AutoShift::new(millis(50), millis(20));
+        under_test.press(ShiftDirection::Left);
+
+        under_test.release(ShiftDirection::Right);
+
+        assert_eq!(Some(ShiftDirection::Left), under_test.direction());
+    }
+}
@@ -1,25 +1,126 @@
+use std::{collections::VecDeque, time::Duration};
+
 use anathema::geometry::LocalPos;
+use rand::{rngs::StdRng, Rng, SeedableRng};
 use smol::channel::Sender;
 
 use crate::GlobalStateManagementMessage;
 
-use super::tetronimo::{Tetronimo, TetronimoShape};
+use super::{
+    game_mode::{GameMode, GameSettings},
+    gravity::{GravityCurve, LineClearGravity},
+    piece_generator::{PieceGenerator, PieceGeneratorType},
+    scoring::{ScoringSystem, ScoringSystemType, TSpin},
+    tetronimo::{RotationDirection, Tetronimo, TetronimoShape},
+};
+
+// The visible part of the arena in blocks, it has to be at least as wide as
+// the I piece.
+pub(crate) const DEFAULT_ARENA_WIDTH: usize = 10;
+pub(crate) const DEFAULT_ARENA_HEIGHT: usize = 20;
+pub(crate) const MIN_ARENA_WIDTH: usize = 4;
+pub(crate) const MAX_ARENA_WIDTH: usize = 20;
+pub(crate) const MIN_ARENA_HEIGHT: usize = 10;
+pub(crate) const MAX_ARENA_HEIGHT: usize = 40;
+
+pub(crate) const MIN_PREVIEW_COUNT: usize = 1;
+pub(crate) const MAX_PREVIEW_COUNT: usize = 6;
+pub(crate) const DEFAULT_PREVIEW_COUNT: usize = 3;
+
+pub(crate) const MAX_START_LEVEL: u16 = 19;
+const LINES_PER_LEVEL: u16 = 10;
+
+pub(crate) const MAX_GARBAGE_HEIGHT: u16 = 5;
+// Rows of garbage for each B-Type height, the same as the NES.
+const GARBAGE_ROWS: [usize; MAX_GARBAGE_HEIGHT as usize + 1] = [0, 3, 5, 8, 10, 12];
+const GARBAGE_DENSITY: f64 = 0.6;
+
+// Sprint games record the time every ten lines.
+pub(crate) const SPLIT_LINES: u16 = 10;
+pub(crate) const DEFAULT_ULTRA_DURATION: Duration = Duration::from_secs(120);
+
+pub(crate) const DEFAULT_LOCK_DELAY: Duration = Duration::from_millis(500);
+pub(crate) const DEFAULT_MOVE_RESET_LIMIT: u16 = 15;
+
+// A T piece rotated in with the last of its wall kicks always counts as a
+// full T-spin, even when it would otherwise only be a mini.
+const T_SPIN_KICK: usize = 4;
+const T_CORNERS: [(isize, isize); 4] = [(0, 0), (2, 0), (0, 2), (2, 2)];
+
+// Frames the completed rows take to be wiped away before the blocks above
+// them fall, zero clears them straight away.
+pub(crate) const DEFAULT_LINE_CLEAR_TICKS: u16 = 10;
+
+// Rows above the visible field that pieces spawn into, the Guideline field
+// is 40 rows tall with only the bottom 20 shown.
+pub(crate) const DEFAULT_HIDDEN_ROWS: usize = 20;
+// New pieces start in the two hidden rows right above the visible field.
+const SPAWN_ROWS: isize = 2;
 
 pub(crate) struct GameLoop {
     arena: Vec<Option<TetronimoShape>>,
 
-    next_piece: Option<TetronimoShape>,
+    next_pieces: VecDeque<TetronimoShape>,
+    preview_count: usize,
     piece: Tetronimo,
+    piece_generator_type: PieceGeneratorType,
+    piece_generator: Box<dyn PieceGenerator>,
+
+    hold_piece: Option<TetronimoShape>,
+    hold_locked: bool,
+
+    // Every game is played from a seed so the exact same piece sequence can be
+    // replayed, the seed is either fixed by the player or picked at random.
+    fixed_seed: Option<u64>,
+    seed: u64,
+    rng: StdRng,
 
     position: Position,
     old_position: Position,
 
+    // A piece resting on the stack locks once the lock delay has passed,
+    // moving it restarts the wait up to the reset limit. No limit means the
+    // piece can be moved around forever.
+    lock_delay: Duration,
+    move_reset_limit: Option<u16>,
+    lock_timer: Option<Duration>,
+    move_resets: u16,
+    lowest_row: isize,
+
+    // The wall kick used if the last thing that moved the piece was a
+    // rotation, T-spins only count when the piece was rotated into place.
+    last_rotation_kick: Option<usize>,
+    t_spin: Option<TSpin>,
+
+    // Locks in a row that cleared lines, and Tetrises or T-spins in a row
+    // with no easier clear in between. Both are None outside of a streak
+    // and count from zero for its first clear.
+    combo: Option<u16>,
+    back_to_back: Option<u16>,
+
+    // Rows being wiped from the centre out, one step every tick.
+    clearing_rows: Vec<usize>,
+    clearing_ticks: u16,
+    line_clear_ticks: u16,
+    line_clear_gravity: LineClearGravity,
+
     arena_size: Position,
+    hidden_rows: usize,
 
     game_state: GameLoopState,
+    game_mode: GameMode,
+    garbage_height: u16,
+    // Time spent playing, it doesn't move while the game is paused.
+    elapsed: Duration,
+    splits: Vec<Duration>,
+    ultra_duration: Duration,
 
-    current_score: u16,
+    current_score: u32,
+    scoring_system: Box<dyn ScoringSystem>,
     current_lines: u16,
+    start_level: u16,
+    level: u16,
+    gravity_curve: GravityCurve,
     shapes_statistics: ShapeStatistics,
 
     tx: Sender<GlobalStateManagementMessage>,
@@ -36,10 +137,22 @@ pub(crate) struct ShapeStatistics {
     pub(crate) z_count: u16,
 }
 
+// How a finished game went, used to decide whether it made the high scores.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct GameResult {
+    pub(crate) mode: GameMode,
+    pub(crate) score: u32,
+    pub(crate) lines: u16,
+    pub(crate) level: u16,
+    pub(crate) time: Duration,
+    pub(crate) splits: Vec<Duration>,
+}
+
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub(crate) enum MoveActionType {
     None,
-    Rotate,
+    Rotate(RotationDirection),
+    SoftDrop,
     Drop,
     MoveLeft,
     MoveRight,
@@ -52,15 +165,21 @@ pub(crate) enum GameLoopState {
     Running,
     Falling,
     Moving(MoveActionType),
+    Holding,
+    Locking,
     PieceBlocked,
     CheckRows,
+    ClearingRows,
     CheckGameOver,
     GameOver,
+    GameWon,
 }
 
+#[derive(Clone, Copy)]
 pub(crate) enum GameAction {
     Pause,
     Move(MoveActionType),
+    Hold,
 }
 
 impl GameLoop {
@@ -72,21 +191,138 @@ impl GameLoop {
         Self {
             arena: vec![None; arena_width * arena_height],
 
-            next_piece: None,
-            piece: Tetronimo::random(),
+            next_pieces: VecDeque::with_capacity(MAX_PREVIEW_COUNT),
+            preview_count: DEFAULT_PREVIEW_COUNT,
+            piece: Tetronimo::new(TetronimoShape::IShape),
+            piece_generator_type: PieceGeneratorType::default(),
+            piece_generator: PieceGeneratorType::default().create(),
+
+            hold_piece: None,
+            hold_locked: false,
+
+            fixed_seed: None,
+            seed: 0,
+            rng: StdRng::seed_from_u64(0),
             position: Position::new(0, 0),
             old_position: Position::new(0, 0),
-            arena_size: Position::new(arena_width, arena_height),
+            lock_delay: DEFAULT_LOCK_DELAY,
+            move_reset_limit: Some(DEFAULT_MOVE_RESET_LIMIT),
+            lock_timer: None,
+            move_resets: 0,
+            lowest_row: 0,
+            last_rotation_kick: None,
+            t_spin: None,
+            combo: None,
+            back_to_back: None,
+            clearing_rows: Vec::new(),
+            clearing_ticks: 0,
+            line_clear_ticks: DEFAULT_LINE_CLEAR_TICKS,
+            line_clear_gravity: LineClearGravity::default(),
+            arena_size: Position::new(arena_width as isize, arena_height as isize),
+            hidden_rows: 0,
             game_state: GameLoopState::Start,
+            game_mode: GameMode::default(),
+            garbage_height: 0,
+            elapsed: Duration::ZERO,
+            splits: Vec::new(),
+            ultra_duration: DEFAULT_ULTRA_DURATION,
 
             current_score: 0,
+            scoring_system: ScoringSystemType::default().create(),
             current_lines: 0,
+            start_level: 0,
+            level: 0,
+            gravity_curve: GravityCurve::default(),
             shapes_statistics: ShapeStatistics::default(),
 
             tx,
         }
     }
 
+    pub(crate) fn with_piece_generator(mut self, generator_type: PieceGeneratorType) -> Self {
+        self.piece_generator_type = generator_type;
+        self.piece_generator = generator_type.create();
+        self
+    }
+
+    pub(crate) fn with_preview_count(mut self, count: usize) -> Self {
+        self.set_preview_count(count);
+        self
+    }
+
+    // Takes effect the next time the preview queue is filled.
+    pub(crate) fn set_preview_count(&mut self, count: usize) {
+        self.preview_count = count.clamp(MIN_PREVIEW_COUNT, MAX_PREVIEW_COUNT);
+    }
+
+    pub(crate) fn with_scoring_system(mut self, scoring_system_type: ScoringSystemType) -> Self {
+        self.scoring_system = scoring_system_type.create();
+        self
+    }
+
+    pub(crate) fn with_gravity_curve(mut self, gravity_curve: GravityCurve) -> Self {
+        self.gravity_curve = gravity_curve;
+        self
+    }
+
+    pub(crate) fn with_lock_delay(mut self, lock_delay: Duration) -> Self {
+        self.lock_delay = lock_delay;
+        self
+    }
+
+    pub(crate) fn with_move_reset_limit(mut self, limit: Option<u16>) -> Self {
+        self.move_reset_limit = limit;
+        self
+    }
+
+    pub(crate) fn with_line_clear_ticks(mut self, ticks: u16) -> Self {
+        self.line_clear_ticks = ticks;
+        self
+    }
+
+    // The arena grows upwards, `draw_arena` still only shows the rows it was
+    // created with.
+    pub(crate) fn with_hidden_rows(mut self, rows: usize) -> Self {
+        self.arena_size.y += rows as isize - self.hidden_rows as isize;
+        self.hidden_rows = rows;
+        self.create_new_arena();
+        self
+    }
+
+    pub(crate) fn with_line_clear_gravity(mut self, gravity: LineClearGravity) -> Self {
+        self.line_clear_gravity = gravity;
+        self
+    }
+
+    pub(crate) fn with_ultra_duration(mut self, duration: Duration) -> Self {
+        self.ultra_duration = duration;
+        self
+    }
+
+    pub(crate) fn set_start_level(&mut self, level: u16) {
+        self.start_level = level.min(MAX_START_LEVEL);
+    }
+
+    pub(crate) fn apply_settings(&mut self, settings: GameSettings) {
+        self.set_start_level(settings.start_level);
+        self.game_mode = settings.mode;
+        self.garbage_height = settings.garbage_height.min(MAX_GARBAGE_HEIGHT);
+    }
+
+    pub(crate) fn arena_width(&self) -> usize {
+        self.arena_size.x as usize
+    }
+
+    // How long it takes the current piece to fall a single row.
+    pub(crate) fn fall_interval(&self) -> Duration {
+        self.gravity_curve.row_duration(self.level)
+    }
+
+    pub(crate) fn with_seed(mut self, seed: Option<u64>) -> Self {
+        self.fixed_seed = seed;
+        self
+    }
+
     pub(crate) fn handle_input(&mut self, game_action: GameAction) {
         match game_action {
             GameAction::Pause => {
@@ -95,10 +331,60 @@ impl GameLoop {
                 }
             }
             GameAction::Move(move_action) => {
-                if self.game_state == GameLoopState::Running {
+                if self.accepts_input() {
                     self.game_state = GameLoopState::Moving(move_action)
                 }
             }
+            GameAction::Hold => {
+                if self.accepts_input() {
+                    self.game_state = GameLoopState::Holding
+                }
+            }
+        }
+    }
+
+    // The piece can be moved while it is falling and while it is waiting to
+    // lock.
+    pub(crate) fn accepts_input(&self) -> bool {
+        matches!(
+            self.game_state,
+            GameLoopState::Running | GameLoopState::Locking
+        )
+    }
+
+    pub(crate) fn advance_time(&mut self, dt: Duration) {
+        if self.game_state == GameLoopState::Start {
+            return;
+        }
+
+        self.elapsed += dt;
+        if self.game_state == GameLoopState::Locking {
+            if let Some(lock_timer) = self.lock_timer.as_mut() {
+                *lock_timer += dt;
+            }
+        }
+
+        if self.game_mode == GameMode::Ultra {
+            self.elapsed = self.elapsed.min(self.ultra_duration);
+            // Wait for the piece to settle so the game never ends half way
+            // through clearing rows.
+            if self.elapsed == self.ultra_duration && self.accepts_input() {
+                self.game_state = GameLoopState::GameWon;
+            }
+        }
+
+        if self.game_mode.is_timed() {
+            let _ = self
+                .tx
+                .try_send(GlobalStateManagementMessage::UpdateTime(self.clock()));
+        }
+    }
+
+    // Ultra counts down the time left, the other modes count the time played.
+    fn clock(&self) -> Duration {
+        match self.game_mode {
+            GameMode::Ultra => self.ultra_duration - self.elapsed,
+            _ => self.elapsed,
         }
     }
 
@@ -108,6 +394,46 @@ impl GameLoop {
         }
     }
 
+    // Falls a row for every interval that fits in `elapsed`, so gravity
+    // faster than the tick rate isn't held back to one row a tick. The state
+    // machine is stepped after each row and the fall stops once the piece
+    // lands. Returns the time carried over to the next tick.
+    pub(crate) fn fall<F>(
+        &mut self,
+        mut elapsed: Duration,
+        interval: Duration,
+        soft_drop: bool,
+        mut step: F,
+    ) -> Duration
+    where
+        F: FnMut(&mut GameLoop),
+    {
+        while elapsed >= interval {
+            elapsed -= interval;
+            match soft_drop {
+                true => self.handle_input(GameAction::Move(MoveActionType::SoftDrop)),
+                false => self.fall_tick(),
+            }
+            step(self);
+
+            if self.game_state != GameLoopState::Running {
+                return Duration::ZERO;
+            }
+        }
+        elapsed
+    }
+
+    pub(crate) fn clear_tick(&mut self) {
+        if self.game_state == GameLoopState::ClearingRows {
+            self.clearing_ticks += 1;
+        }
+    }
+
+    // Input waits until the rows have gone and the next piece is out.
+    pub(crate) fn is_clearing_rows(&self) -> bool {
+        self.game_state == GameLoopState::ClearingRows
+    }
+
     pub(crate) fn do_state_machine<S, L, T, N>(
         &mut self,
         update_score: S,
@@ -115,9 +441,9 @@ impl GameLoop {
         update_next: N,
         update_statistics: T,
     ) where
-        S: FnMut(u16),
+        S: FnMut(u32),
         L: FnMut(u16),
-        N: FnMut(TetronimoShape),
+        N: FnMut(Vec<TetronimoShape>),
         T: FnMut(ShapeStatistics),
     {
         self.old_position = self.position.clone();
@@ -129,16 +455,18 @@ impl GameLoop {
             GameLoopState::Running => (),
             GameLoopState::Falling => self.handle_falling(),
             GameLoopState::Moving(game_move_type) => {
-                self.handle_movement_state(&game_move_type);
+                self.handle_movement_state(&game_move_type, update_score);
             }
+            GameLoopState::Holding => self.handle_hold(update_next, update_statistics),
+            GameLoopState::Locking => self.handle_locking(),
             GameLoopState::PieceBlocked => self.handle_piece_blocked(),
-            GameLoopState::CheckRows => {
-                self.handle_check_rows(update_score, update_line, update_statistics)
-            }
+            GameLoopState::CheckRows => self.handle_check_rows(update_score, update_line),
+            GameLoopState::ClearingRows => self.handle_clearing_rows(update_score, update_line),
             GameLoopState::CheckGameOver => {
                 self.handle_check_game_over(update_next, update_statistics)
             }
             GameLoopState::GameOver => self.handle_game_over(),
+            GameLoopState::GameWon => self.handle_game_won(),
         }
     }
 
@@ -149,202 +477,501 @@ impl GameLoop {
         update_next_piece: P,
         update_statistics: T,
     ) where
-        S: FnMut(u16),
+        S: FnMut(u32),
         L: FnMut(u16),
-        P: FnMut(TetronimoShape),
+        P: FnMut(Vec<TetronimoShape>),
         T: FnMut(ShapeStatistics),
     {
+        self.reseed();
+        self.hold_piece = None;
+        self.update_hold(false);
         self.create_new_piece(update_next_piece, update_statistics);
         self.create_new_arena();
+        if self.game_mode == GameMode::BType {
+            self.add_garbage();
+        }
         self.current_score = 0;
         self.current_lines = 0;
+        self.update_streaks(None, None);
+        self.elapsed = Duration::ZERO;
+        self.splits.clear();
+        self.level = self.start_level;
+        let _ = self
+            .tx
+            .try_send(GlobalStateManagementMessage::UpdateLevel(self.level));
         update_score(self.current_score);
-        update_lines(self.current_lines);
+        update_lines(self.lines_counter());
         self.game_state = GameLoopState::Running;
     }
 
+    // Restart the random sequence so the game only depends on the seed, any
+    // pieces left over in the generator from the last game are thrown away.
+    fn reseed(&mut self) {
+        self.seed = self.fixed_seed.unwrap_or_else(rand::random);
+        self.rng = StdRng::seed_from_u64(self.seed);
+        self.piece_generator = self.piece_generator_type.create();
+        self.next_pieces.clear();
+    }
+
     fn handle_falling(&mut self) {
-        let (shape, width) = self.piece.get_chars();
-        if shape
-            .iter()
-            .enumerate()
-            .filter(|(offset, present)| {
-                let x = self.position.x + offset % width;
-                let y = ((self.position.y) + offset / width) + 1;
-                let array_pos = (self.arena_size.x * y) + x;
-                **present && (y >= self.arena_size.y || self.arena[array_pos].is_some())
-            })
-            .count()
-            == 0
-        {
+        if !self.is_blocked_below(&self.position) {
             self.position.y += 1;
+            self.last_rotation_kick = None;
+        }
+        self.update_lock(false);
+    }
+
+    // Called after every fall or move. A piece that has come to rest on the
+    // stack starts waiting to lock, a successful move while it waits restarts
+    // the wait as long as there are resets left. Reaching a new lowest row
+    // gives the piece all of its resets back.
+    fn update_lock(&mut self, moved: bool) {
+        if self.position.y > self.lowest_row {
+            self.lowest_row = self.position.y;
+            self.move_resets = 0;
+        }
+
+        if !self.is_blocked_below(&self.position) {
+            self.lock_timer = None;
             self.game_state = GameLoopState::Running;
-        } else {
+            return;
+        }
+
+        let can_reset = self
+            .move_reset_limit
+            .is_none_or(|limit| self.move_resets < limit);
+        match self.lock_timer {
+            Some(_) if moved && can_reset => {
+                self.lock_timer = Some(Duration::ZERO);
+                self.move_resets += 1;
+            }
+            Some(_) => (),
+            // A piece that used up its resets and was lifted off the stack
+            // locks as soon as it lands again.
+            None if !can_reset && self.move_resets > 0 => {
+                self.game_state = GameLoopState::PieceBlocked;
+                return;
+            }
+            None => self.lock_timer = Some(Duration::ZERO),
+        }
+        self.game_state = GameLoopState::Locking;
+    }
+
+    fn handle_locking(&mut self) {
+        if self
+            .lock_timer
+            .is_some_and(|lock_timer| lock_timer >= self.lock_delay)
+        {
             self.game_state = GameLoopState::PieceBlocked;
         }
     }
 
-    fn handle_movement_state(&mut self, game_move_type: &MoveActionType) {
-        match game_move_type {
-            MoveActionType::None => (),
-            MoveActionType::Rotate => self.handle_rotate(),
-            MoveActionType::Drop => self.handle_drop(),
+    // Every new piece gets a fresh lock delay and all of its resets.
+    fn reset_lock(&mut self) {
+        self.lock_timer = None;
+        self.move_resets = 0;
+        self.lowest_row = self.position.y;
+        self.last_rotation_kick = None;
+    }
+
+    fn is_blocked_below(&self, position: &Position) -> bool {
+        let (shape, width) = self.piece.get_chars();
+        self.collides(shape, width, &Position::new(position.x, position.y + 1))
+    }
+
+    // Check if a shape placed at the given position would leave the arena or
+    // overlap any block that has already been placed.
+    fn collides(&self, shape: &[bool], width: usize, position: &Position) -> bool {
+        shape.iter().enumerate().any(|(offset, present)| {
+            let x = position.x + (offset % width) as isize;
+            let y = position.y + (offset / width) as isize;
+            *present
+                && (x < 0
+                    || y < 0
+                    || x >= self.arena_size.x
+                    || y >= self.arena_size.y
+                    || self.arena[self.arena_index(x, y)].is_some())
+        })
+    }
+
+    fn arena_index(&self, x: isize, y: isize) -> usize {
+        ((self.arena_size.x * y) + x) as usize
+    }
+
+    fn handle_movement_state<S>(&mut self, game_move_type: &MoveActionType, update_score: S)
+    where
+        S: FnMut(u32),
+    {
+        self.game_state = GameLoopState::Running;
+        let moved = match game_move_type {
+            MoveActionType::None => false,
+            MoveActionType::Rotate(direction) => self.handle_rotate(direction),
+            MoveActionType::SoftDrop => self.handle_soft_drop(update_score),
+            MoveActionType::Drop => {
+                self.handle_drop(update_score);
+                return;
+            }
             MoveActionType::MoveLeft => self.handle_move_left(),
             MoveActionType::MoveRight => self.handle_move_right(),
-        }
-        self.game_state = GameLoopState::Running;
+        };
+        self.update_lock(moved);
     }
 
-    fn handle_rotate(&mut self) {
-        let (shape, width) = self.piece.get_next_rotation_chars();
-        if shape
+    // Rotation follows the Super Rotation System, if the rotated shape does not
+    // fit where it is each wall kick for the transition is tried in order.
+    fn handle_rotate(&mut self, direction: &RotationDirection) -> bool {
+        let (shape, width) = self.piece.get_next_rotation_chars(direction);
+        let kick = self
+            .piece
+            .get_wall_kicks(direction)
             .iter()
+            .map(|(x, y)| Position::new(self.position.x + x, self.position.y - y))
             .enumerate()
-            .filter(|(offset, present)| {
-                let x = self.position.x + offset % width;
-                let y = self.position.y + offset / width;
-                let array_pos = (self.arena_size.x * y) + x;
-                **present
-                    && (x >= self.arena_size.x
-                        || y >= self.arena_size.y
-                        || self.arena[array_pos].is_some())
-            })
-            .count()
-            == 0
-        {
-            self.piece.rotate();
+            .find(|(_, position)| !self.collides(shape, width, position));
+
+        match kick {
+            Some((kick, position)) => {
+                self.position = position;
+                self.piece.rotate(direction);
+                self.last_rotation_kick = Some(kick);
+                true
+            }
+            None => false,
         }
     }
 
-    fn handle_drop(&mut self) {}
+    // Soft drop replaces a regular fall while the drop key is held, it moves
+    // the piece a single row but rewards the player for doing so. Once the
+    // piece reaches the stack it waits for the lock delay like any other.
+    fn handle_soft_drop<S>(&mut self, mut update_score: S) -> bool
+    where
+        S: FnMut(u32),
+    {
+        if self.is_blocked_below(&self.position) {
+            return false;
+        }
+
+        self.position.y += 1;
+        self.last_rotation_kick = None;
+        self.current_score += self.scoring_system.soft_drop(1);
+        update_score(self.current_score);
+        true
+    }
+
+    // Hard drop, the piece goes straight to the lowest row it can reach and
+    // is locked immediately rather than waiting for the next fall tick.
+    fn handle_drop<S>(&mut self, mut update_score: S)
+    where
+        S: FnMut(u32),
+    {
+        let landing_position = self.landing_position();
+        let distance = (landing_position.y - self.position.y) as u16;
+        self.position = landing_position;
+        if distance > 0 {
+            self.last_rotation_kick = None;
+        }
+
+        let points = self.scoring_system.hard_drop(distance);
+        if points > 0 {
+            self.current_score += points;
+            update_score(self.current_score);
+        }
+        self.game_state = GameLoopState::PieceBlocked;
+    }
+
+    // The lowest position the current piece can reach by dropping straight down.
+    fn landing_position(&self) -> Position {
+        let mut position = self.position.clone();
+        while !self.is_blocked_below(&position) {
+            position.y += 1;
+        }
+        position
+    }
+
+    fn handle_move_left(&mut self) -> bool {
+        self.shift(-1)
+    }
+
+    fn handle_move_right(&mut self) -> bool {
+        self.shift(1)
+    }
 
-    fn handle_move_left(&mut self) {
+    fn shift(&mut self, dx: isize) -> bool {
         let (shape, width) = self.piece.get_chars();
-        if shape
-            .iter()
-            .enumerate()
-            .filter(|(offset, present)| {
-                let x = offset % width;
-                let y = offset / width;
-                let position_on_arena = Position {
-                    x: self.position.x + x,
-                    y: self.position.y + y,
-                };
-                if **present {
-                    if position_on_arena.x == 0 {
-                        return true; // Block is at the left edge of the arena
-                    } else if self.arena
-                        [(position_on_arena.y * self.arena_size.x) + position_on_arena.x - 1]
-                        .is_some()
-                    {
-                        return true; // Block is blocked by another block
-                    }
-                }
-                false
-            })
-            .count()
-            == 0
-        {
-            self.position.x -= 1;
+        let position = Position::new(self.position.x + dx, self.position.y);
+        let free = !self.collides(shape, width, &position);
+        if free {
+            self.position = position;
+            self.last_rotation_kick = None;
+        }
+        free
+    }
+
+    // The current piece is swapped with the one in the hold slot, or with the
+    // next piece if nothing has been held yet. Holding is only allowed once
+    // until the piece has been locked in place.
+    fn handle_hold<P, T>(&mut self, update_next_piece: P, update_statistics: T)
+    where
+        P: FnMut(Vec<TetronimoShape>),
+        T: FnMut(ShapeStatistics),
+    {
+        self.game_state = GameLoopState::Running;
+        if self.hold_locked {
+            return;
+        }
+
+        match self.hold_piece.replace(self.piece.shape.clone()) {
+            Some(shape) => {
+                self.piece = Tetronimo::new(shape);
+                self.position = self.spawn_position();
+                self.reset_lock();
+            }
+            None => self.create_new_piece(update_next_piece, update_statistics),
+        }
+        self.update_hold(true);
+
+        let (blocks, width) = self.piece.get_chars();
+        if self.collides(blocks, width, &self.position) {
+            self.game_state = GameLoopState::GameOver;
         }
     }
 
-    fn handle_move_right(&mut self) {
+    fn update_hold(&mut self, locked: bool) {
+        self.hold_locked = locked;
+        let _ = self.tx.try_send(GlobalStateManagementMessage::UpdateHoldTetronimo(
+            self.hold_piece.clone(),
+            locked,
+        ));
+    }
+
+    // A piece that locks without any of it showing in the visible field is a
+    // lock out and ends the game.
+    fn handle_piece_blocked(&mut self) {
+        self.t_spin = self.detect_t_spin();
+        let locked_out = self.piece_rows().all(|y| y < self.hidden_rows as isize);
+        self.add_piece_to_arena();
+        self.game_state = match locked_out {
+            true => GameLoopState::GameOver,
+            false => GameLoopState::CheckRows,
+        };
+    }
+
+    fn piece_rows(&self) -> impl Iterator<Item = isize> + '_ {
         let (shape, width) = self.piece.get_chars();
-        if shape
+        shape
             .iter()
             .enumerate()
-            .filter(|(offset, present)| {
-                let x = offset % width;
-                let y = offset / width;
-                let position_on_arena = Position {
-                    x: self.position.x + x,
-                    y: self.position.y + y,
-                };
-                if **present {
-                    if (position_on_arena.x + 1) >= self.arena_size.x {
-                        return true; // Block is at the right edge of the arena
-                    } else if self.arena
-                        [(position_on_arena.y * self.arena_size.x) + position_on_arena.x + 1]
-                        .is_some()
-                    {
-                        return true; // Block is blocked by another block
-                    }
-                    return false;
-                }
-                false
-            })
-            .count()
-            == 0
-        {
-            self.position.x += 1;
+            .filter(|(_, present)| **present)
+            .map(move |(offset, _)| self.position.y + (offset / width) as isize)
+    }
+
+    fn handle_check_rows<S, L>(&mut self, update_score: S, update_line: L)
+    where
+        S: FnMut(u32),
+        L: FnMut(u16),
+    {
+        self.clearing_rows = self.complete_rows();
+        self.clearing_ticks = 0;
+
+        if self.clearing_rows.is_empty() {
+            self.score_lines(0, update_score, update_line);
+            self.game_state = GameLoopState::CheckGameOver;
+        } else {
+            self.game_state = GameLoopState::ClearingRows;
+            self.handle_clearing_rows(update_score, update_line);
         }
     }
 
-    fn handle_piece_blocked(&mut self) {
-        self.add_piece_to_arena();
-        self.game_state = GameLoopState::CheckRows;
+    fn handle_clearing_rows<S, L>(&mut self, update_score: S, update_line: L)
+    where
+        S: FnMut(u32),
+        L: FnMut(u16),
+    {
+        if self.clearing_ticks < self.line_clear_ticks {
+            return;
+        }
+
+        let rows = std::mem::take(&mut self.clearing_rows);
+        match self.line_clear_gravity {
+            LineClearGravity::Naive => self.shift_rows_down(&rows),
+            LineClearGravity::Cascade => {
+                for row in &rows {
+                    self.drop_rows(*row);
+                }
+                self.drop_blocks();
+            }
+        }
+
+        self.game_state = GameLoopState::CheckGameOver;
+        self.score_lines(rows.len() as u16, update_score, update_line);
+
+        // Blocks cascading into place can complete more rows.
+        if self.game_state != GameLoopState::GameWon {
+            self.clearing_rows = self.complete_rows();
+            self.clearing_ticks = 0;
+            if !self.clearing_rows.is_empty() {
+                self.game_state = GameLoopState::ClearingRows;
+            }
+        }
     }
 
-    fn handle_check_rows<S, L, T>(
-        &mut self,
-        mut update_score: S,
-        mut update_line: L,
-        _update_statistics: T,
-    ) where
-        S: FnMut(u16),
+    fn score_lines<S, L>(&mut self, complete_row: u16, mut update_score: S, mut update_line: L)
+    where
+        S: FnMut(u32),
         L: FnMut(u16),
-        T: FnMut(ShapeStatistics),
     {
-        let complete_row = self.remove_complete_rows();
+        let t_spin = self.t_spin.take();
+        let mut points = match t_spin {
+            Some(t_spin) => self.scoring_system.t_spin(t_spin, complete_row, self.level),
+            None => self.scoring_system.line_clear(complete_row, self.level),
+        };
 
-        if complete_row > 0 {
-            self.current_score += complete_row * complete_row;
+        // A T-spin that clears nothing keeps the back-to-back streak going
+        // without adding to it, any other lock without a clear ends the combo.
+        let difficult = complete_row == 4 || (t_spin.is_some() && complete_row > 0);
+        let combo = match complete_row {
+            0 => None,
+            _ => Some(self.combo.map_or(0, |combo| combo + 1)),
+        };
+        let back_to_back = match (complete_row, difficult) {
+            (0, _) => self.back_to_back,
+            (_, true) => Some(self.back_to_back.map_or(0, |streak| streak + 1)),
+            (_, false) => None,
+        };
+        if difficult && back_to_back > Some(0) {
+            points = self.scoring_system.back_to_back(points);
+        }
+        if let Some(combo) = combo {
+            points += self.scoring_system.combo(combo, self.level);
+        }
+        self.update_streaks(combo, back_to_back);
+
+        if points > 0 {
+            self.current_score += points;
             update_score(self.current_score);
+        }
+        if let Some(t_spin) = t_spin {
+            let _ = self
+                .tx
+                .try_send(GlobalStateManagementMessage::TSpin(t_spin, complete_row));
+        }
 
+        if complete_row > 0 {
             self.current_lines += complete_row;
-            update_line(self.current_lines);
-        } else {
-            self.game_state = GameLoopState::CheckGameOver;
-        }
-    }
+            update_line(self.lines_counter());
+            self.update_level();
 
-    fn remove_complete_rows(&mut self) -> u16 {
-        let mut complete_row = 0;
-        for y in 0..self.arena_size.y {
-            let mut complete = true;
-            for x in 0..self.arena_size.x {
-                if self.arena[(self.arena_size.x * y) + x].is_none() {
-                    complete = false;
+            if self.game_mode == GameMode::Sprint {
+                while self.splits.len() < (self.current_lines / SPLIT_LINES) as usize {
+                    self.splits.push(self.elapsed);
                 }
             }
 
-            if complete {
-                self.drop_rows(y);
-                self.drop_blocks();
-                complete_row += 1;
+            if self
+                .game_mode
+                .line_goal()
+                .is_some_and(|goal| self.current_lines >= goal)
+            {
+                self.game_state = GameLoopState::GameWon;
             }
         }
-        complete_row
+    }
+
+    // The first clear of a streak isn't shown, so only the counts after it
+    // are sent.
+    fn update_streaks(&mut self, combo: Option<u16>, back_to_back: Option<u16>) {
+        let (old_combo, old_back_to_back) = (self.combo, self.back_to_back);
+        self.combo = combo;
+        self.back_to_back = back_to_back;
+
+        if combo.unwrap_or_default() != old_combo.unwrap_or_default() {
+            let _ = self.tx.try_send(GlobalStateManagementMessage::UpdateCombo(
+                combo.unwrap_or_default(),
+            ));
+        }
+        if back_to_back.unwrap_or_default() != old_back_to_back.unwrap_or_default() {
+            let _ = self
+                .tx
+                .try_send(GlobalStateManagementMessage::UpdateBackToBack(
+                    back_to_back.unwrap_or_default(),
+                ));
+        }
+    }
+
+    // The 3-corner rule, a T rotated into place with at least three of the
+    // corners around its centre filled is a T-spin. It is only a mini unless
+    // both corners it points towards are filled, or it got there with the
+    // last wall kick.
+    fn detect_t_spin(&self) -> Option<TSpin> {
+        let kick = self.last_rotation_kick?;
+        if self.piece.shape != TetronimoShape::TShape {
+            return None;
+        }
+
+        let filled = |(x, y): (isize, isize)| {
+            let (x, y) = (self.position.x + x, self.position.y + y);
+            x < 0
+                || y < 0
+                || x >= self.arena_size.x
+                || y >= self.arena_size.y
+                || self.arena[self.arena_index(x, y)].is_some()
+        };
+        let corners = T_CORNERS.into_iter().filter(|corner| filled(*corner));
+        if corners.count() < 3 {
+            return None;
+        }
+
+        match kick == T_SPIN_KICK || self.piece.front_corners().into_iter().all(filled) {
+            true => Some(TSpin::Full),
+            false => Some(TSpin::Mini),
+        }
+    }
+
+    // Modes with a line goal count down the lines left, the others count the
+    // lines cleared.
+    fn lines_counter(&self) -> u16 {
+        match self.game_mode.line_goal() {
+            Some(goal) => goal.saturating_sub(self.current_lines),
+            None => self.current_lines,
+        }
+    }
+
+    // Every ten lines cleared moves the player up a level from where they
+    // started, modes with a line goal stay on the level they started on.
+    fn update_level(&mut self) {
+        if self.game_mode.line_goal().is_some() {
+            return;
+        }
+
+        let level = self.start_level + (self.current_lines / LINES_PER_LEVEL);
+        if level != self.level {
+            self.level = level;
+            let _ = self
+                .tx
+                .try_send(GlobalStateManagementMessage::UpdateLevel(self.level));
+        }
+    }
+
+    fn complete_rows(&self) -> Vec<usize> {
+        let width = self.arena_size.x as usize;
+        self.arena
+            .chunks(width)
+            .enumerate()
+            .filter(|(_, row)| row.iter().all(Option::is_some))
+            .map(|(y, _)| y)
+            .collect()
     }
 
     fn handle_check_game_over<P, T>(&mut self, update_next_piece: P, update_statistics: T)
     where
-        P: FnMut(TetronimoShape),
+        P: FnMut(Vec<TetronimoShape>),
         T: FnMut(ShapeStatistics),
     {
         self.create_new_piece(update_next_piece, update_statistics);
+        self.update_hold(false);
         let (blocks, width) = self.piece.get_chars();
-        let mut overlap = false;
-        blocks.iter().enumerate().for_each(|(offset, present)| {
-            let x = self.position.x + offset % width;
-            let y = self.position.y + offset / width;
-            if *present && self.arena[x + (self.arena_size.x * y)].is_some() {
-                overlap = true;
-            }
-        });
 
-        if overlap {
+        if self.collides(blocks, width, &self.position) {
             self.game_state = GameLoopState::GameOver;
         } else {
             self.game_state = GameLoopState::Running;
@@ -352,29 +979,50 @@ impl GameLoop {
     }
 
     fn handle_game_over(&mut self) {
+        self.finish_game(GlobalStateManagementMessage::GameOver);
+    }
+
+    fn handle_game_won(&mut self) {
+        self.finish_game(GlobalStateManagementMessage::GameWon);
+    }
+
+    fn finish_game(&mut self, outcome: GlobalStateManagementMessage) {
         self.game_state = GameLoopState::Start;
-        let _ = self.tx.try_send(GlobalStateManagementMessage::GameOver);
+        let _ = self
+            .tx
+            .try_send(GlobalStateManagementMessage::UpdateSeed(self.seed));
+        let _ = self
+            .tx
+            .try_send(GlobalStateManagementMessage::GameResult(GameResult {
+                mode: self.game_mode,
+                score: self.current_score,
+                lines: self.current_lines,
+                level: self.level,
+                time: self.elapsed,
+                splits: self.splits.clone(),
+            }));
+        let _ = self.tx.try_send(outcome);
     }
 
     fn add_piece_to_arena(&mut self) {
         let (blocks, width) = self.piece.get_chars();
         blocks.iter().enumerate().for_each(|(offset, present)| {
-            let x = self.position.x + offset % width;
-            let y = self.position.y + offset / width;
+            let x = self.position.x + (offset % width) as isize;
+            let y = self.position.y + (offset / width) as isize;
             if *present {
-                self.arena[x + (self.arena_size.x * y)] = Some(self.piece.shape.clone());
+                self.arena[((self.arena_size.x * y) + x) as usize] = Some(self.piece.shape.clone());
             }
         });
     }
 
     fn create_new_piece<P, T>(&mut self, mut update_next_piece: P, mut update_statistics: T)
     where
-        P: FnMut(TetronimoShape),
+        P: FnMut(Vec<TetronimoShape>),
         T: FnMut(ShapeStatistics),
     {
-        self.piece = match &self.next_piece {
-            Some(piece) => Tetronimo::new(piece.clone()),
-            None => Tetronimo::random(),
+        self.piece = match self.next_pieces.pop_front() {
+            Some(piece) => Tetronimo::new(piece),
+            None => Tetronimo::new(self.piece_generator.next_piece(&mut self.rng)),
         };
 
         match self.piece.shape {
@@ -388,30 +1036,78 @@ impl GameLoop {
         };
 
         update_statistics(self.shapes_statistics);
-        self.next_piece = Some(rand::random());
-        self.position = Position::new(self.arena_size.x / 2, 0);
+        while self.next_pieces.len() < self.preview_count {
+            let next_piece = self.piece_generator.next_piece(&mut self.rng);
+            self.next_pieces.push_back(next_piece);
+        }
+        self.position = self.spawn_position();
+        self.reset_lock();
+
+        update_next_piece(self.next_pieces.iter().cloned().collect());
+    }
 
-        if let Some(next_piece) = &self.next_piece {
-            update_next_piece(next_piece.clone());
+    // Pieces move down a row as soon as they spawn if there is room, so the
+    // bottom of the piece shows at the top of the field. Spawning on top of
+    // the stack is a block out and ends the game.
+    fn spawn_position(&self) -> Position {
+        let (shape, width) = self.piece.get_chars();
+        let x = (self.arena_size.x - width as isize) / 2;
+        let y = (self.hidden_rows as isize - SPAWN_ROWS).max(0);
+        let below = Position::new(x, y + 1);
+        match self.hidden_rows > 0 && !self.collides(shape, width, &below) {
+            true => below,
+            false => Position::new(x, y),
         }
     }
 
     fn create_new_arena(&mut self) {
-        self.arena = vec![None; self.arena_size.x * self.arena_size.y];
+        self.arena = vec![None; (self.arena_size.x * self.arena_size.y) as usize];
     }
 
-    pub(crate) fn draw_piece<F>(&self, mut func: F)
+    // B-Type games start with rows of random blocks at the bottom of the
+    // arena, every row has a gap so none of them start out complete.
+    fn add_garbage(&mut self) {
+        let (width, height) = (self.arena_size.x as usize, self.arena_size.y as usize);
+        let rows = GARBAGE_ROWS[self.garbage_height as usize].min(height);
+
+        for y in (height - rows)..height {
+            let gap = self.rng.random_range(0..width);
+            for x in 0..width {
+                if x != gap && self.rng.random_bool(GARBAGE_DENSITY) {
+                    self.arena[(width * y) + x] = Some(self.rng.random());
+                }
+            }
+        }
+    }
+
+    pub(crate) fn draw_piece<F>(&self, func: F)
+    where
+        F: FnMut(&TetronimoShape, LocalPos),
+    {
+        self.draw_piece_at(&self.position, func);
+    }
+
+    // The ghost is a projection of the current piece where it would land if
+    // it was dropped now.
+    pub(crate) fn draw_ghost<F>(&self, func: F)
+    where
+        F: FnMut(&TetronimoShape, LocalPos),
+    {
+        self.draw_piece_at(&self.landing_position(), func);
+    }
+
+    fn draw_piece_at<F>(&self, position: &Position, mut func: F)
     where
         F: FnMut(&TetronimoShape, LocalPos),
     {
         let (shape, width) = self.piece.get_chars();
         shape.iter().enumerate().for_each(|(offset, present)| {
             if *present {
-                let x = (offset % width) as u16;
-                let y = (offset / width) as u16;
-                let local_pos = LocalPos::new(x, y);
-                let pos: LocalPos = self.position.clone().into();
-                func(&self.piece.shape, local_pos + pos);
+                let x = position.x + (offset % width) as isize;
+                let y = position.y + (offset / width) as isize - self.hidden_rows as isize;
+                if y >= 0 {
+                    func(&self.piece.shape, Position::new(x, y).into());
+                }
             }
         });
     }
@@ -420,47 +1116,78 @@ impl GameLoop {
     where
         D: FnMut(Option<&TetronimoShape>, LocalPos),
     {
-        self.arena.iter().enumerate().for_each(|(offset, piece)| {
-            let x = offset % self.arena_size.x;
-            let y = offset / self.arena_size.x;
-            let local_pos = LocalPos::new(x as u16, y as u16);
+        let width = self.arena_size.x as usize;
+        let wiped = self.wiped_columns();
+        let hidden = self.hidden_rows * width;
+        self.arena.iter().enumerate().skip(hidden).for_each(|(offset, piece)| {
+            let x = offset % width;
+            let y = offset / width;
+            let local_pos = LocalPos::new(x as u16, (y - self.hidden_rows) as u16);
+            // Distance from the centre, the two middle columns are both 0.
+            let distance = (2 * x + 1).abs_diff(width) / 2;
             match piece {
+                Some(_) if distance < wiped && self.clearing_rows.contains(&y) => {
+                    draw(None, local_pos)
+                }
                 Some(piece) => draw(Some(piece), local_pos),
                 None => draw(None, local_pos),
             }
         });
     }
 
+    // Completed rows are wiped like the NES, a pair of columns at a time
+    // from the centre out to the walls.
+    fn wiped_columns(&self) -> usize {
+        let half_width = (self.arena_size.x as usize).div_ceil(2);
+        let ticks = self.line_clear_ticks.max(1) as usize;
+        half_width * (self.clearing_ticks as usize).min(ticks) / ticks
+    }
+
+    // Removes the cleared rows and moves everything above them down by the
+    // number of rows cleared below it, empty rows come in at the top.
+    fn shift_rows_down(&mut self, rows: &[usize]) {
+        let width = self.arena_size.x as usize;
+        let mut arena = vec![None; rows.len() * width];
+        arena.extend(
+            self.arena
+                .chunks(width)
+                .enumerate()
+                .filter(|(y, _)| !rows.contains(y))
+                .flat_map(|(_, row)| row.iter().cloned()),
+        );
+        self.arena = arena;
+    }
+
     // A completed row has been removed now it is time to drop all the blocks
     // into place.
     fn drop_rows(&mut self, row: usize) {
         // Remove row from the arena
-        let row_offset = row * self.arena_size.x;
-        for col_offset in 0..self.arena_size.x {
+        let width = self.arena_size.x as usize;
+        let row_offset = row * width;
+        for col_offset in 0..width {
             self.arena[row_offset + col_offset] = None;
         }
     }
 
     fn drop_blocks(&mut self) {
+        let width = self.arena_size.x as usize;
         loop {
             let mut changed = false;
-            for block in 0..(self.arena_size.x * self.arena_size.y) {
+            for block in 0..self.arena.len() {
                 if self.arena[block].is_none() {
                     // Cant drop an empty block
                     continue;
-                } else if (block + self.arena_size.x) >= (self.arena_size.x * self.arena_size.y) {
+                } else if (block + width) >= self.arena.len() {
                     // Reached end of the board
                     continue;
-                } else if self.arena[block + self.arena_size.x].is_none() {
-                    self.arena[block + self.arena_size.x] = self.arena[block].clone();
+                } else if self.arena[block + width].is_none() {
+                    self.arena[block + width] = self.arena[block].clone();
                     self.arena[block] = None;
                     changed = true;
                 }
             }
 
-            if changed {
-                self.drop_blocks();
-            } else {
+            if !changed {
                 break;
             }
         }
@@ -471,14 +1198,14 @@ impl GameLoop {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 struct Position {
-    pub x: usize,
-    pub y: usize,
+    pub x: isize,
+    pub y: isize,
 }
 
 impl Position {
-    fn new(x: usize, y: usize) -> Self {
+    fn new(x: isize, y: isize) -> Self {
         Self { x, y }
     }
 }
@@ -491,16 +1218,29 @@ impl From<Position> for LocalPos {
 
 #[cfg(test)]
 mod test {
-    use crate::core::tetronimo::TetronimoShape;
+    use std::time::Duration;
+
+    use crate::core::tetronimo::{RotationDirection, Tetronimo, TetronimoShape};
 
-    use super::GameLoop;
+    use super::{
+        GameAction, GameLoop, GameLoopState, GameResult, MoveActionType, Position,
+        DEFAULT_HIDDEN_ROWS, MAX_GARBAGE_HEIGHT, T_SPIN_KICK,
+    };
+    use crate::core::game_mode::{GameMode, GameSettings};
+    use crate::core::gravity::{GravityCurve, LineClearGravity};
+    use crate::core::scoring::{ScoringSystemType, TSpin};
+    use crate::GlobalStateManagementMessage;
+
+    fn run_state_machine(game_loop: &mut GameLoop) {
+        game_loop.do_state_machine(|_| (), |_| (), |_| (), |_| ());
+    }
 
     #[test]
     fn calculate_rows_when_empty() {
         let (tx, _rx) = smol::channel::unbounded();
-        let mut under_test = GameLoop::new(2, 2, tx);
+        let under_test = GameLoop::new(2, 2, tx);
 
-        assert_eq!(0, under_test.remove_complete_rows());
+        assert!(under_test.complete_rows().is_empty());
     }
 
     #[test]
@@ -511,7 +1251,7 @@ mod test {
         under_test.arena[4] = Some(TetronimoShape::IShape);
         under_test.arena[5] = Some(TetronimoShape::IShape);
 
-        assert_eq!(1, under_test.remove_complete_rows());
+        assert_eq!(vec![1], under_test.complete_rows());
     }
 
     #[test]
@@ -525,7 +1265,7 @@ mod test {
         under_test.arena[4] = Some(TetronimoShape::ZShape);
         under_test.arena[5] = Some(TetronimoShape::ZShape);
 
-        assert_eq!(2, under_test.remove_complete_rows());
+        assert_eq!(vec![0, 1], under_test.complete_rows());
     }
 
     #[test]
@@ -541,4 +1281,1322 @@ mod test {
         assert_eq!(Some(TetronimoShape::JShape), under_test.arena[2]);
         assert_eq!(None, under_test.arena[3]);
     }
+
+    #[test]
+    fn hard_drop_to_floor() {
+        let (tx, _rx) = smol::channel::unbounded();
+        let mut under_test = GameLoop::new(4, 6, tx);
+        under_test.piece = Tetronimo::new(TetronimoShape::OShape);
+        under_test.position = Position::new(1, 0);
+        under_test.game_state = GameLoopState::Running;
+
+        under_test.handle_input(GameAction::Move(MoveActionType::Drop));
+        run_state_machine(&mut under_test);
+
+        assert_eq!(4, under_test.position.y);
+        assert_eq!(GameLoopState::PieceBlocked, under_test.game_state);
+    }
+
+    #[test]
+    fn hard_drop_lands_on_stack() {
+        let (tx, _rx) = smol::channel::unbounded();
+        let mut under_test = GameLoop::new(4, 6, tx);
+        under_test.arena[(4 * 5) + 1] = Some(TetronimoShape::JShape);
+        under_test.piece = Tetronimo::new(TetronimoShape::OShape);
+        under_test.position = Position::new(1, 0);
+        under_test.game_state = GameLoopState::Running;
+
+        under_test.handle_input(GameAction::Move(MoveActionType::Drop));
+        run_state_machine(&mut under_test);
+
+        assert_eq!(3, under_test.position.y);
+    }
+
+    #[test]
+    fn hard_drop_awards_points_per_row() {
+        let (tx, _rx) = smol::channel::unbounded();
+        let mut under_test = GameLoop::new(4, 6, tx);
+        under_test.piece = Tetronimo::new(TetronimoShape::OShape);
+        under_test.position = Position::new(1, 0);
+        under_test.game_state = GameLoopState::Running;
+
+        let mut reported_score = 0;
+        under_test.handle_input(GameAction::Move(MoveActionType::Drop));
+        under_test.do_state_machine(|score| reported_score = score, |_| (), |_| (), |_| ());
+
+        assert_eq!(8, under_test.current_score);
+        assert_eq!(8, reported_score);
+    }
+
+    #[test]
+    fn hard_drop_locks_piece_into_arena() {
+        let (tx, _rx) = smol::channel::unbounded();
+        let mut under_test = GameLoop::new(4, 6, tx);
+        under_test.piece = Tetronimo::new(TetronimoShape::OShape);
+        under_test.position = Position::new(1, 0);
+        under_test.game_state = GameLoopState::Running;
+
+        under_test.handle_input(GameAction::Move(MoveActionType::Drop));
+        run_state_machine(&mut under_test);
+        run_state_machine(&mut under_test);
+
+        assert_eq!(GameLoopState::CheckRows, under_test.game_state);
+        assert_eq!(Some(TetronimoShape::OShape), under_test.arena[(4 * 4) + 1]);
+        assert_eq!(Some(TetronimoShape::OShape), under_test.arena[(4 * 4) + 2]);
+        assert_eq!(Some(TetronimoShape::OShape), under_test.arena[(4 * 5) + 1]);
+        assert_eq!(Some(TetronimoShape::OShape), under_test.arena[(4 * 5) + 2]);
+    }
+
+    #[test]
+    fn soft_drop_moves_one_row_and_scores() {
+        let (tx, _rx) = smol::channel::unbounded();
+        let mut under_test = GameLoop::new(4, 6, tx);
+        under_test.piece = Tetronimo::new(TetronimoShape::OShape);
+        under_test.position = Position::new(1, 0);
+        under_test.game_state = GameLoopState::Running;
+
+        let mut reported_score = 0;
+        under_test.handle_input(GameAction::Move(MoveActionType::SoftDrop));
+        under_test.do_state_machine(|score| reported_score = score, |_| (), |_| (), |_| ());
+
+        assert_eq!(1, under_test.position.y);
+        assert_eq!(1, reported_score);
+        assert_eq!(GameLoopState::Running, under_test.game_state);
+    }
+
+    #[test]
+    fn soft_drop_on_floor_waits_to_lock() {
+        let (tx, _rx) = smol::channel::unbounded();
+        let mut under_test = GameLoop::new(4, 6, tx);
+        under_test.piece = Tetronimo::new(TetronimoShape::OShape);
+        under_test.position = Position::new(1, 4);
+        under_test.game_state = GameLoopState::Running;
+
+        under_test.handle_input(GameAction::Move(MoveActionType::SoftDrop));
+        run_state_machine(&mut under_test);
+
+        assert_eq!(4, under_test.position.y);
+        assert_eq!(0, under_test.current_score);
+        assert_eq!(GameLoopState::Locking, under_test.game_state);
+    }
+
+    // An O piece resting on the floor of an empty 4x6 arena.
+    fn landed_piece(game_loop: GameLoop) -> GameLoop {
+        let mut game_loop = game_loop;
+        game_loop.piece = Tetronimo::new(TetronimoShape::OShape);
+        game_loop.position = Position::new(1, 3);
+        game_loop.game_state = GameLoopState::Running;
+        game_loop.fall_tick();
+        run_state_machine(&mut game_loop);
+        game_loop
+    }
+
+    fn wait(game_loop: &mut GameLoop, millis: u64) {
+        game_loop.advance_time(Duration::from_millis(millis));
+        run_state_machine(game_loop);
+    }
+
+    fn slide(game_loop: &mut GameLoop, action: MoveActionType) {
+        game_loop.handle_input(GameAction::Move(action));
+        run_state_machine(game_loop);
+    }
+
+    #[test]
+    fn landing_waits_for_the_lock_delay() {
+        let (tx, _rx) = smol::channel::unbounded();
+        let mut under_test = landed_piece(GameLoop::new(4, 6, tx));
+        assert_eq!(4, under_test.position.y);
+        assert_eq!(GameLoopState::Locking, under_test.game_state);
+
+        wait(&mut under_test, 300);
+        wait(&mut under_test, 199);
+        assert_eq!(GameLoopState::Locking, under_test.game_state);
+
+        wait(&mut under_test, 1);
+        assert_eq!(GameLoopState::PieceBlocked, under_test.game_state);
+    }
+
+    #[test]
+    fn lock_delay_is_configurable() {
+        let (tx, _rx) = smol::channel::unbounded();
+        let game_loop = GameLoop::new(4, 6, tx).with_lock_delay(Duration::from_millis(100));
+        let mut under_test = landed_piece(game_loop);
+
+        wait(&mut under_test, 100);
+
+        assert_eq!(GameLoopState::PieceBlocked, under_test.game_state);
+    }
+
+    fn piece_rows(game_loop: &GameLoop) -> Vec<u16> {
+        let mut rows = vec![];
+        game_loop.draw_piece(|_, position| rows.push(position.y));
+        rows
+    }
+
+    #[test]
+    fn fall_carries_over_the_time_left() {
+        let (tx, _rx) = smol::channel::unbounded();
+        let mut under_test = GameLoop::new(10, 20, tx);
+        run_state_machine(&mut under_test);
+        let spawned = piece_rows(&under_test);
+
+        let left = under_test.fall(
+            Duration::from_millis(25),
+            Duration::from_millis(10),
+            false,
+            run_state_machine,
+        );
+
+        let fallen: Vec<_> = spawned.iter().map(|row| row + 2).collect();
+        assert_eq!(fallen, piece_rows(&under_test));
+        assert_eq!(Duration::from_millis(5), left);
+    }
+
+    #[test]
+    fn gravity_faster_than_a_tick_falls_several_rows() {
+        let (tx, _rx) = smol::channel::unbounded();
+        let mut under_test = GameLoop::new(10, 20, tx).with_gravity_curve(GravityCurve::Guideline);
+        run_state_machine(&mut under_test);
+        under_test.level = 19;
+        let mut ghost = vec![];
+        under_test.draw_ghost(|_, position| ghost.push(position.y));
+
+        let tick = Duration::from_millis(16);
+        assert!(under_test.fall_interval() < tick);
+        let left = under_test.fall(tick, under_test.fall_interval(), false, run_state_machine);
+
+        assert_eq!(ghost, piece_rows(&under_test));
+        assert_eq!(GameLoopState::Locking, under_test.game_state);
+        assert_eq!(Duration::ZERO, left);
+    }
+
+    #[test]
+    fn fall_ticks_do_not_lock_a_waiting_piece() {
+        let (tx, _rx) = smol::channel::unbounded();
+        let mut under_test = landed_piece(GameLoop::new(4, 6, tx));
+
+        under_test.fall_tick();
+        run_state_machine(&mut under_test);
+
+        assert_eq!(GameLoopState::Locking, under_test.game_state);
+    }
+
+    #[test]
+    fn moving_a_waiting_piece_restarts_the_delay() {
+        let (tx, _rx) = smol::channel::unbounded();
+        let mut under_test = landed_piece(GameLoop::new(4, 6, tx));
+
+        wait(&mut under_test, 400);
+        slide(&mut under_test, MoveActionType::MoveLeft);
+        wait(&mut under_test, 400);
+        assert_eq!(GameLoopState::Locking, under_test.game_state);
+
+        wait(&mut under_test, 100);
+        assert_eq!(GameLoopState::PieceBlocked, under_test.game_state);
+    }
+
+    #[test]
+    fn failed_moves_do_not_restart_the_delay() {
+        let (tx, _rx) = smol::channel::unbounded();
+        let mut under_test = landed_piece(GameLoop::new(4, 6, tx));
+        slide(&mut under_test, MoveActionType::MoveLeft);
+
+        wait(&mut under_test, 400);
+        slide(&mut under_test, MoveActionType::MoveLeft);
+        wait(&mut under_test, 100);
+
+        assert_eq!(0, under_test.position.x);
+        assert_eq!(GameLoopState::PieceBlocked, under_test.game_state);
+    }
+
+    #[test]
+    fn move_resets_run_out() {
+        let (tx, _rx) = smol::channel::unbounded();
+        let mut under_test = landed_piece(GameLoop::new(4, 6, tx));
+
+        for turn in 0..15 {
+            let action = match turn % 2 {
+                0 => MoveActionType::MoveLeft,
+                _ => MoveActionType::MoveRight,
+            };
+            wait(&mut under_test, 400);
+            slide(&mut under_test, action);
+        }
+        assert_eq!(GameLoopState::Locking, under_test.game_state);
+
+        wait(&mut under_test, 400);
+        slide(&mut under_test, MoveActionType::MoveRight);
+        wait(&mut under_test, 100);
+        assert_eq!(GameLoopState::PieceBlocked, under_test.game_state);
+    }
+
+    #[test]
+    fn without_resets_the_delay_still_applies_once() {
+        let (tx, _rx) = smol::channel::unbounded();
+        let game_loop = GameLoop::new(4, 6, tx).with_move_reset_limit(Some(0));
+        let mut under_test = landed_piece(game_loop);
+        assert_eq!(GameLoopState::Locking, under_test.game_state);
+
+        wait(&mut under_test, 400);
+        slide(&mut under_test, MoveActionType::MoveLeft);
+        wait(&mut under_test, 100);
+
+        assert_eq!(GameLoopState::PieceBlocked, under_test.game_state);
+    }
+
+    #[test]
+    fn infinite_resets_never_lock_a_moving_piece() {
+        let (tx, _rx) = smol::channel::unbounded();
+        let game_loop = GameLoop::new(4, 6, tx).with_move_reset_limit(None);
+        let mut under_test = landed_piece(game_loop);
+
+        for turn in 0..100 {
+            let action = match turn % 2 {
+                0 => MoveActionType::MoveLeft,
+                _ => MoveActionType::MoveRight,
+            };
+            wait(&mut under_test, 400);
+            slide(&mut under_test, action);
+        }
+
+        assert_eq!(GameLoopState::Locking, under_test.game_state);
+    }
+
+    #[test]
+    fn sliding_off_a_ledge_cancels_the_delay() {
+        let (tx, _rx) = smol::channel::unbounded();
+        let mut under_test = GameLoop::new(4, 6, tx);
+        // A ledge two blocks wide and two rows high on the right.
+        for y in 4..6 {
+            for x in 2..4 {
+                under_test.arena[y * 4 + x] = Some(TetronimoShape::IShape);
+            }
+        }
+        under_test.piece = Tetronimo::new(TetronimoShape::OShape);
+        under_test.position = Position::new(2, 2);
+        under_test.game_state = GameLoopState::Running;
+        under_test.fall_tick();
+        run_state_machine(&mut under_test);
+        assert_eq!(GameLoopState::Locking, under_test.game_state);
+
+        slide(&mut under_test, MoveActionType::MoveLeft);
+        slide(&mut under_test, MoveActionType::MoveLeft);
+
+        assert_eq!(GameLoopState::Running, under_test.game_state);
+        assert_eq!(None, under_test.lock_timer);
+    }
+
+    #[test]
+    fn out_of_resets_landing_again_locks_immediately() {
+        let (tx, _rx) = smol::channel::unbounded();
+        let game_loop = GameLoop::new(4, 6, tx).with_move_reset_limit(Some(1));
+        let mut under_test = landed_piece(game_loop);
+        assert_eq!(GameLoopState::Locking, under_test.game_state);
+        under_test.move_resets = 1;
+        under_test.lock_timer = None;
+        under_test.game_state = GameLoopState::Running;
+
+        under_test.fall_tick();
+        run_state_machine(&mut under_test);
+
+        assert_eq!(GameLoopState::PieceBlocked, under_test.game_state);
+    }
+
+    #[test]
+    fn a_new_lowest_row_gives_resets_back() {
+        let (tx, _rx) = smol::channel::unbounded();
+        let game_loop = GameLoop::new(4, 6, tx).with_move_reset_limit(Some(1));
+        let mut under_test = landed_piece(game_loop);
+        under_test.move_resets = 1;
+        under_test.lowest_row = 3;
+
+        under_test.update_lock(false);
+
+        assert_eq!(0, under_test.move_resets);
+        assert_eq!(4, under_test.lowest_row);
+    }
+
+    fn rotate(game_loop: &mut GameLoop, direction: RotationDirection) {
+        game_loop.game_state = GameLoopState::Running;
+        game_loop.handle_input(GameAction::Move(MoveActionType::Rotate(direction)));
+        run_state_machine(game_loop);
+    }
+
+    fn occupied_cells(game_loop: &GameLoop) -> Vec<(isize, isize)> {
+        let (shape, width) = game_loop.piece.get_chars();
+        shape
+            .iter()
+            .enumerate()
+            .filter(|(_, present)| **present)
+            .map(|(offset, _)| {
+                (
+                    game_loop.position.x + (offset % width) as isize,
+                    game_loop.position.y + (offset / width) as isize,
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn rotate_in_open_space_does_not_kick() {
+        let (tx, _rx) = smol::channel::unbounded();
+        let mut under_test = GameLoop::new(10, 10, tx);
+        under_test.piece = Tetronimo::new(TetronimoShape::TShape);
+        under_test.position = Position::new(4, 4);
+
+        rotate(&mut under_test, RotationDirection::Clockwise);
+
+        assert_eq!(Position::new(4, 4), under_test.position);
+        assert_eq!(vec![(5, 4), (5, 5), (6, 5), (5, 6)], occupied_cells(&under_test));
+    }
+
+    #[test]
+    fn rotate_counter_clockwise() {
+        let (tx, _rx) = smol::channel::unbounded();
+        let mut under_test = GameLoop::new(10, 10, tx);
+        under_test.piece = Tetronimo::new(TetronimoShape::TShape);
+        under_test.position = Position::new(4, 4);
+
+        rotate(&mut under_test, RotationDirection::CounterClockwise);
+
+        assert_eq!(Position::new(4, 4), under_test.position);
+        assert_eq!(vec![(5, 4), (4, 5), (5, 5), (5, 6)], occupied_cells(&under_test));
+    }
+
+    #[test]
+    fn rotate_kicks_off_left_wall() {
+        let (tx, _rx) = smol::channel::unbounded();
+        let mut under_test = GameLoop::new(10, 10, tx);
+        under_test.piece = Tetronimo::new(TetronimoShape::TShape);
+        under_test.piece.rotate(&RotationDirection::Clockwise);
+        under_test.position = Position::new(-1, 4);
+
+        rotate(&mut under_test, RotationDirection::Clockwise);
+
+        assert_eq!(Position::new(0, 4), under_test.position);
+        assert_eq!(vec![(0, 5), (1, 5), (2, 5), (1, 6)], occupied_cells(&under_test));
+    }
+
+    #[test]
+    fn rotate_i_shape_kicks_off_right_wall() {
+        let (tx, _rx) = smol::channel::unbounded();
+        let mut under_test = GameLoop::new(10, 10, tx);
+        under_test.piece = Tetronimo::new(TetronimoShape::IShape);
+        under_test.piece.rotate(&RotationDirection::Clockwise);
+        under_test.position = Position::new(7, 4);
+
+        rotate(&mut under_test, RotationDirection::Clockwise);
+
+        assert_eq!(Position::new(6, 4), under_test.position);
+        assert_eq!(vec![(6, 6), (7, 6), (8, 6), (9, 6)], occupied_cells(&under_test));
+    }
+
+    #[test]
+    fn rotate_kicks_up_off_floor() {
+        let (tx, _rx) = smol::channel::unbounded();
+        let mut under_test = GameLoop::new(10, 10, tx);
+        under_test.piece = Tetronimo::new(TetronimoShape::TShape);
+        under_test.position = Position::new(1, 8);
+        under_test.arena[(10 * 9) + 4] = Some(TetronimoShape::OShape);
+
+        rotate(&mut under_test, RotationDirection::Clockwise);
+
+        assert_eq!(Position::new(0, 7), under_test.position);
+        assert_eq!(vec![(1, 7), (1, 8), (2, 8), (1, 9)], occupied_cells(&under_test));
+    }
+
+    #[test]
+    fn rotate_rejected_when_no_kick_fits() {
+        let (tx, _rx) = smol::channel::unbounded();
+        let mut under_test = GameLoop::new(3, 3, tx);
+        under_test.piece = Tetronimo::new(TetronimoShape::IShape);
+        under_test.position = Position::new(-1, 1);
+        under_test.arena = vec![None; 9];
+        under_test.arena_size = Position::new(3, 3);
+
+        rotate(&mut under_test, RotationDirection::Clockwise);
+
+        assert_eq!(Position::new(-1, 1), under_test.position);
+    }
+
+    // Builds an arena from rows of '#' and '.', top row first.
+    fn build_arena(
+        rows: &[&str],
+    ) -> (GameLoop, smol::channel::Receiver<GlobalStateManagementMessage>) {
+        let (tx, rx) = smol::channel::unbounded();
+        let mut game_loop =
+            GameLoop::new(rows[0].len(), rows.len(), tx).with_line_clear_ticks(0);
+        for (y, row) in rows.iter().enumerate() {
+            for (x, cell) in row.chars().enumerate() {
+                if cell == '#' {
+                    game_loop.arena[y * row.len() + x] = Some(TetronimoShape::OShape);
+                }
+            }
+        }
+        (game_loop, rx)
+    }
+
+    // An arena with a T piece at the given position.
+    fn t_arena(
+        rows: &[&str],
+        position: Position,
+    ) -> (GameLoop, smol::channel::Receiver<GlobalStateManagementMessage>) {
+        let (mut game_loop, rx) = build_arena(rows);
+        game_loop.piece = Tetronimo::new(TetronimoShape::TShape);
+        game_loop.position = position;
+        (game_loop, rx)
+    }
+
+    fn lock(game_loop: &mut GameLoop) {
+        game_loop.game_state = GameLoopState::PieceBlocked;
+        run_state_machine(game_loop);
+        run_state_machine(game_loop);
+    }
+
+    fn t_spins(rx: &smol::channel::Receiver<GlobalStateManagementMessage>) -> Vec<(TSpin, u16)> {
+        std::iter::from_fn(|| rx.try_recv().ok())
+            .filter_map(|message| match message {
+                GlobalStateManagementMessage::TSpin(t_spin, lines) => Some((t_spin, lines)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn rotating_into_a_t_slot_is_a_t_spin() {
+        let (mut under_test, rx) = t_arena(
+            &[".....", "...#.", "#...#", "##.##"],
+            Position::new(1, 1),
+        );
+        under_test.piece.rotate(&RotationDirection::Clockwise);
+
+        rotate(&mut under_test, RotationDirection::Clockwise);
+        lock(&mut under_test);
+
+        assert_eq!(vec![(TSpin::Full, 2)], t_spins(&rx));
+        assert_eq!(1200, under_test.current_score);
+    }
+
+    #[test]
+    fn t_spin_missing_a_front_corner_is_a_mini() {
+        let (mut under_test, rx) = t_arena(
+            &[".....", ".#.#.", "#...#", "##..."],
+            Position::new(1, 1),
+        );
+        under_test.piece.rotate(&RotationDirection::Clockwise);
+
+        rotate(&mut under_test, RotationDirection::Clockwise);
+        lock(&mut under_test);
+
+        assert_eq!(vec![(TSpin::Mini, 1)], t_spins(&rx));
+        assert_eq!(200, under_test.current_score);
+    }
+
+    #[test]
+    fn t_spin_needs_the_rotation_to_be_the_last_move() {
+        let (mut under_test, rx) = t_arena(
+            &[".....", ".....", "#....", "#...."],
+            Position::new(1, 0),
+        );
+        rotate(&mut under_test, RotationDirection::Clockwise);
+        rotate(&mut under_test, RotationDirection::CounterClockwise);
+
+        under_test.handle_input(GameAction::Move(MoveActionType::Drop));
+        run_state_machine(&mut under_test);
+        run_state_machine(&mut under_test);
+
+        assert_eq!(Position::new(1, 2), under_test.position);
+        assert!(t_spins(&rx).is_empty());
+    }
+
+    #[test]
+    fn t_spin_with_the_last_kick_is_never_a_mini() {
+        let (mut under_test, _rx) = t_arena(
+            &[".....", ".#.#.", "#...#", "##..#"],
+            Position::new(1, 1),
+        );
+        under_test.piece.rotate(&RotationDirection::Clockwise);
+        under_test.piece.rotate(&RotationDirection::Clockwise);
+        under_test.last_rotation_kick = Some(T_SPIN_KICK);
+
+        assert_eq!(Some(TSpin::Full), under_test.detect_t_spin());
+    }
+
+    fn drawn_rows(game_loop: &GameLoop) -> Vec<String> {
+        let width = game_loop.arena_size.x as usize;
+        let height = game_loop.arena_size.y as usize - game_loop.hidden_rows;
+        let mut cells = vec!['.'; width * height];
+        game_loop.draw_arena(|piece, position| {
+            if piece.is_some() {
+                cells[position.y as usize * width + position.x as usize] = '#';
+            }
+        });
+        cells.chunks(width).map(|row| row.iter().collect()).collect()
+    }
+
+    #[test]
+    fn completed_rows_are_wiped_from_the_centre_out() {
+        let (tx, _rx) = smol::channel::unbounded();
+        let mut under_test = GameLoop::new(6, 3, tx).with_line_clear_ticks(3);
+        under_test.arena[6] = Some(TetronimoShape::OShape);
+        for cell in &mut under_test.arena[12..] {
+            *cell = Some(TetronimoShape::OShape);
+        }
+        under_test.game_state = GameLoopState::CheckRows;
+
+        run_state_machine(&mut under_test);
+        assert!(under_test.is_clearing_rows());
+        assert_eq!(vec!["......", "#.....", "######"], drawn_rows(&under_test));
+
+        under_test.clear_tick();
+        run_state_machine(&mut under_test);
+        assert_eq!(vec!["......", "#.....", "##..##"], drawn_rows(&under_test));
+
+        under_test.clear_tick();
+        run_state_machine(&mut under_test);
+        assert_eq!(vec!["......", "#.....", "#....#"], drawn_rows(&under_test));
+
+        under_test.clear_tick();
+        run_state_machine(&mut under_test);
+        assert!(!under_test.is_clearing_rows());
+        assert_eq!(vec!["......", "......", "#....."], drawn_rows(&under_test));
+        assert_eq!(1, under_test.current_lines);
+    }
+
+    #[test]
+    fn hidden_rows_are_not_drawn() {
+        let (tx, _rx) = smol::channel::unbounded();
+        let mut under_test = GameLoop::new(3, 2, tx).with_hidden_rows(2);
+        under_test.arena[1] = Some(TetronimoShape::OShape);
+        under_test.arena[6] = Some(TetronimoShape::OShape);
+
+        assert_eq!(12, under_test.arena.len());
+        assert_eq!(vec!["#..", "..."], drawn_rows(&under_test));
+    }
+
+    #[test]
+    fn pieces_spawn_just_above_the_visible_field() {
+        let (tx, _rx) = smol::channel::unbounded();
+        let mut under_test = GameLoop::new(10, 20, tx).with_hidden_rows(DEFAULT_HIDDEN_ROWS);
+        run_state_machine(&mut under_test);
+
+        let mut drawn_rows = vec![];
+        under_test.draw_piece(|_, position| drawn_rows.push(position.y));
+
+        assert_eq!(19, under_test.position.y);
+        assert!(!drawn_rows.is_empty());
+        assert!(drawn_rows.iter().all(|y| *y == 0));
+    }
+
+    #[test]
+    fn spawning_into_the_stack_is_a_block_out() {
+        let (tx, _rx) = smol::channel::unbounded();
+        let mut under_test = GameLoop::new(4, 4, tx)
+            .with_hidden_rows(4)
+            .with_line_clear_ticks(0);
+        run_state_machine(&mut under_test);
+        // Every row but the top two has a gap on the right, so none clear.
+        for (offset, cell) in under_test.arena.iter_mut().enumerate().skip(8) {
+            if offset % 4 != 3 {
+                *cell = Some(TetronimoShape::OShape);
+            }
+        }
+
+        under_test.game_state = GameLoopState::CheckRows;
+        run_state_machine(&mut under_test);
+        run_state_machine(&mut under_test);
+
+        assert_eq!(GameLoopState::GameOver, under_test.game_state);
+    }
+
+    #[test]
+    fn locking_above_the_visible_field_is_a_lock_out() {
+        let (tx, _rx) = smol::channel::unbounded();
+        let mut under_test = GameLoop::new(4, 4, tx).with_hidden_rows(4);
+        under_test.piece = Tetronimo::new(TetronimoShape::OShape);
+
+        under_test.position = Position::new(1, 2);
+        under_test.game_state = GameLoopState::PieceBlocked;
+        run_state_machine(&mut under_test);
+        assert_eq!(GameLoopState::GameOver, under_test.game_state);
+
+        under_test.position = Position::new(1, 3);
+        under_test.game_state = GameLoopState::PieceBlocked;
+        run_state_machine(&mut under_test);
+        assert_eq!(GameLoopState::CheckRows, under_test.game_state);
+    }
+
+    #[test]
+    fn naive_gravity_moves_rows_down_as_they_are() {
+        let (mut under_test, _rx) =
+            build_arena(&[".#..", "##.#", "....", "####", "#.##", "####"]);
+        under_test.game_state = GameLoopState::CheckRows;
+
+        run_state_machine(&mut under_test);
+
+        assert_eq!(
+            vec!["....", "....", ".#..", "##.#", "....", "#.##"],
+            drawn_rows(&under_test)
+        );
+        assert_eq!(2, under_test.current_lines);
+        assert_eq!(GameLoopState::CheckGameOver, under_test.game_state);
+    }
+
+    #[test]
+    fn cascade_gravity_drops_every_block_and_chains_clears() {
+        let (under_test, _rx) = build_arena(&[".#..", "....", "####", "#.##"]);
+        let mut under_test = under_test.with_line_clear_gravity(LineClearGravity::Cascade);
+        under_test.game_state = GameLoopState::CheckRows;
+
+        run_state_machine(&mut under_test);
+        assert_eq!(vec!["....", "....", "....", "####"], drawn_rows(&under_test));
+        assert_eq!(1, under_test.current_lines);
+
+        run_state_machine(&mut under_test);
+        assert_eq!(vec!["....", "....", "....", "...."], drawn_rows(&under_test));
+        assert_eq!(2, under_test.current_lines);
+    }
+
+    #[test]
+    fn piece_cannot_move_while_rows_are_clearing() {
+        let (tx, _rx) = smol::channel::unbounded();
+        let mut under_test = GameLoop::new(4, 4, tx).with_line_clear_ticks(2);
+        for cell in &mut under_test.arena[12..] {
+            *cell = Some(TetronimoShape::OShape);
+        }
+        under_test.game_state = GameLoopState::CheckRows;
+        run_state_machine(&mut under_test);
+
+        under_test.handle_input(GameAction::Move(MoveActionType::MoveLeft));
+        under_test.handle_input(GameAction::Hold);
+        under_test.fall_tick();
+
+        assert_eq!(GameLoopState::ClearingRows, under_test.game_state);
+    }
+
+    // Fills the bottom rows and scores them as if a piece had just locked.
+    fn clear_rows(game_loop: &mut GameLoop, rows: usize) {
+        let width = game_loop.arena_size.x as usize;
+        let start = game_loop.arena.len() - rows * width;
+        for cell in &mut game_loop.arena[start..] {
+            *cell = Some(TetronimoShape::OShape);
+        }
+        game_loop.game_state = GameLoopState::CheckRows;
+        run_state_machine(game_loop);
+    }
+
+    fn streaks(rx: &smol::channel::Receiver<GlobalStateManagementMessage>) -> (Vec<u16>, Vec<u16>) {
+        let mut streaks = (Vec::new(), Vec::new());
+        while let Ok(message) = rx.try_recv() {
+            match message {
+                GlobalStateManagementMessage::UpdateCombo(combo) => streaks.0.push(combo),
+                GlobalStateManagementMessage::UpdateBackToBack(streak) => streaks.1.push(streak),
+                _ => (),
+            }
+        }
+        streaks
+    }
+
+    #[test]
+    fn line_clears_in_a_row_build_a_combo() {
+        let (tx, rx) = smol::channel::unbounded();
+        let mut under_test = GameLoop::new(4, 6, tx).with_line_clear_ticks(0);
+
+        clear_rows(&mut under_test, 1);
+        clear_rows(&mut under_test, 1);
+        clear_rows(&mut under_test, 2);
+        clear_rows(&mut under_test, 0);
+
+        assert_eq!(100 + 150 + 400, under_test.current_score);
+        assert_eq!((vec![1, 2, 0], vec![]), streaks(&rx));
+    }
+
+    #[test]
+    fn tetrises_in_a_row_are_back_to_back() {
+        let (tx, rx) = smol::channel::unbounded();
+        let mut under_test = GameLoop::new(4, 6, tx).with_line_clear_ticks(0);
+
+        clear_rows(&mut under_test, 4);
+        clear_rows(&mut under_test, 0);
+        clear_rows(&mut under_test, 4);
+        clear_rows(&mut under_test, 1);
+
+        assert_eq!(800 + 1200 + 150, under_test.current_score);
+        assert_eq!((vec![1], vec![1, 0]), streaks(&rx));
+    }
+
+    #[test]
+    fn nes_scoring_has_no_streak_bonus() {
+        let (tx, _rx) = smol::channel::unbounded();
+        let mut under_test = GameLoop::new(4, 6, tx)
+            .with_line_clear_ticks(0)
+            .with_scoring_system(ScoringSystemType::Nes);
+
+        clear_rows(&mut under_test, 4);
+        clear_rows(&mut under_test, 4);
+
+        assert_eq!(1200 + 1200, under_test.current_score);
+    }
+
+    fn piece_sequence(seed: u64) -> Vec<TetronimoShape> {
+        let (tx, _rx) = smol::channel::unbounded();
+        let mut under_test = GameLoop::new(10, 20, tx).with_seed(Some(seed));
+        run_state_machine(&mut under_test);
+
+        (0..50)
+            .map(|_| {
+                under_test.create_new_piece(|_| (), |_| ());
+                under_test.piece.shape.clone()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn same_seed_gives_same_pieces() {
+        assert_eq!(piece_sequence(1234), piece_sequence(1234));
+    }
+
+    #[test]
+    fn different_seed_gives_different_pieces() {
+        assert_ne!(piece_sequence(1234), piece_sequence(4321));
+    }
+
+    #[test]
+    fn restarting_replays_the_seed() {
+        let (tx, _rx) = smol::channel::unbounded();
+        let mut under_test = GameLoop::new(10, 20, tx).with_seed(Some(99));
+        run_state_machine(&mut under_test);
+        let first_game: Vec<TetronimoShape> = (0..10)
+            .map(|_| {
+                under_test.create_new_piece(|_| (), |_| ());
+                under_test.piece.shape.clone()
+            })
+            .collect();
+
+        under_test.initialise();
+        run_state_machine(&mut under_test);
+        let second_game: Vec<TetronimoShape> = (0..10)
+            .map(|_| {
+                under_test.create_new_piece(|_| (), |_| ());
+                under_test.piece.shape.clone()
+            })
+            .collect();
+
+        assert_eq!(first_game, second_game);
+    }
+
+    #[test]
+    fn game_over_reports_result() {
+        let (tx, rx) = smol::channel::unbounded();
+        let mut under_test = GameLoop::new(10, 20, tx);
+        run_state_machine(&mut under_test);
+        while rx.try_recv().is_ok() {}
+
+        under_test.current_score = 1200;
+        under_test.current_lines = 14;
+        under_test.level = 1;
+        under_test.elapsed = Duration::from_secs(30);
+        under_test.game_state = GameLoopState::GameOver;
+        run_state_machine(&mut under_test);
+
+        let result = std::iter::from_fn(|| rx.try_recv().ok()).find_map(|message| match message {
+            GlobalStateManagementMessage::GameResult(result) => Some(result),
+            _ => None,
+        });
+        assert_eq!(
+            result,
+            Some(GameResult {
+                mode: GameMode::AType,
+                score: 1200,
+                lines: 14,
+                level: 1,
+                time: Duration::from_secs(30),
+                splits: vec![],
+            })
+        );
+    }
+
+    #[test]
+    fn game_over_reports_seed() {
+        let (tx, rx) = smol::channel::unbounded();
+        let mut under_test = GameLoop::new(10, 20, tx).with_seed(Some(99));
+        run_state_machine(&mut under_test);
+        while rx.try_recv().is_ok() {}
+
+        under_test.game_state = GameLoopState::GameOver;
+        run_state_machine(&mut under_test);
+
+        assert!(matches!(
+            rx.try_recv(),
+            Ok(GlobalStateManagementMessage::UpdateSeed(99))
+        ));
+        assert!(matches!(
+            rx.try_recv(),
+            Ok(GlobalStateManagementMessage::GameResult(_))
+        ));
+        assert!(matches!(
+            rx.try_recv(),
+            Ok(GlobalStateManagementMessage::GameOver)
+        ));
+    }
+
+    fn hold(game_loop: &mut GameLoop) {
+        game_loop.handle_input(GameAction::Hold);
+        run_state_machine(game_loop);
+    }
+
+    #[test]
+    fn hold_into_empty_slot_takes_next_piece() {
+        let (tx, _rx) = smol::channel::unbounded();
+        let mut under_test = GameLoop::new(10, 20, tx);
+        run_state_machine(&mut under_test);
+        let current = under_test.piece.shape.clone();
+        let next = under_test.next_pieces.front().cloned();
+
+        hold(&mut under_test);
+
+        assert_eq!(Some(current), under_test.hold_piece);
+        assert_eq!(next, Some(under_test.piece.shape.clone()));
+        assert!(under_test.hold_locked);
+        assert_eq!(GameLoopState::Running, under_test.game_state);
+    }
+
+    #[test]
+    fn hold_swaps_with_held_piece() {
+        let (tx, _rx) = smol::channel::unbounded();
+        let mut under_test = GameLoop::new(10, 20, tx);
+        run_state_machine(&mut under_test);
+        under_test.piece = Tetronimo::new(TetronimoShape::TShape);
+        under_test.hold_piece = Some(TetronimoShape::IShape);
+        under_test.position = Position::new(0, 5);
+
+        hold(&mut under_test);
+
+        assert_eq!(TetronimoShape::IShape, under_test.piece.shape);
+        assert_eq!(Some(TetronimoShape::TShape), under_test.hold_piece);
+        assert_eq!(Position::new(3, 0), under_test.position);
+    }
+
+    #[test]
+    fn hold_only_once_per_piece() {
+        let (tx, _rx) = smol::channel::unbounded();
+        let mut under_test = GameLoop::new(10, 20, tx);
+        run_state_machine(&mut under_test);
+        under_test.piece = Tetronimo::new(TetronimoShape::TShape);
+        under_test.hold_piece = Some(TetronimoShape::IShape);
+
+        hold(&mut under_test);
+        hold(&mut under_test);
+
+        assert_eq!(TetronimoShape::IShape, under_test.piece.shape);
+        assert_eq!(Some(TetronimoShape::TShape), under_test.hold_piece);
+    }
+
+    #[test]
+    fn hold_unlocks_when_next_piece_spawns() {
+        let (tx, rx) = smol::channel::unbounded();
+        let mut under_test = GameLoop::new(10, 20, tx);
+        run_state_machine(&mut under_test);
+        hold(&mut under_test);
+
+        under_test.game_state = GameLoopState::CheckGameOver;
+        run_state_machine(&mut under_test);
+
+        assert!(!under_test.hold_locked);
+        let last_hold_update = std::iter::from_fn(|| rx.try_recv().ok())
+            .filter_map(|message| match message {
+                GlobalStateManagementMessage::UpdateHoldTetronimo(shape, locked) => {
+                    Some((shape, locked))
+                }
+                _ => None,
+            })
+            .last();
+        assert_eq!(Some((under_test.hold_piece.clone(), false)), last_hold_update);
+    }
+
+    #[test]
+    fn preview_queue_is_filled_to_count() {
+        let (tx, _rx) = smol::channel::unbounded();
+        let mut under_test = GameLoop::new(10, 20, tx).with_preview_count(5);
+
+        let mut previews = vec![];
+        under_test.do_state_machine(|_| (), |_| (), |queue| previews = queue, |_| ());
+
+        assert_eq!(5, previews.len());
+        assert_eq!(under_test.next_pieces, previews);
+    }
+
+    #[test]
+    fn preview_queue_feeds_next_piece() {
+        let (tx, _rx) = smol::channel::unbounded();
+        let mut under_test = GameLoop::new(10, 20, tx).with_preview_count(4);
+        run_state_machine(&mut under_test);
+        let expected: Vec<TetronimoShape> = under_test.next_pieces.iter().cloned().collect();
+
+        let spawned: Vec<TetronimoShape> = (0..4)
+            .map(|_| {
+                under_test.create_new_piece(|_| (), |_| ());
+                under_test.piece.shape.clone()
+            })
+            .collect();
+
+        assert_eq!(expected, spawned);
+        assert_eq!(4, under_test.next_pieces.len());
+    }
+
+    #[test]
+    fn preview_count_is_clamped() {
+        let (tx, _rx) = smol::channel::unbounded();
+        assert_eq!(1, GameLoop::new(10, 20, tx.clone()).with_preview_count(0).preview_count);
+        assert_eq!(6, GameLoop::new(10, 20, tx).with_preview_count(10).preview_count);
+    }
+
+    #[test]
+    fn ghost_is_drawn_at_landing_position() {
+        let (tx, _rx) = smol::channel::unbounded();
+        let mut under_test = GameLoop::new(4, 6, tx);
+        under_test.arena[(4 * 5) + 2] = Some(TetronimoShape::JShape);
+        under_test.piece = Tetronimo::new(TetronimoShape::OShape);
+        under_test.position = Position::new(1, 0);
+
+        let mut ghost = vec![];
+        under_test.draw_ghost(|_, position| ghost.push((position.x, position.y)));
+
+        assert_eq!(vec![(1, 3), (2, 3), (1, 4), (2, 4)], ghost);
+        assert_eq!(Position::new(1, 0), under_test.position);
+    }
+
+    fn fill_rows(game_loop: &mut GameLoop, rows: usize) {
+        let width = game_loop.arena_size.x as usize;
+        let start = game_loop.arena.len() - (rows * width);
+        for block in &mut game_loop.arena[start..] {
+            *block = Some(TetronimoShape::IShape);
+        }
+    }
+
+    #[test]
+    fn level_starts_at_start_level() {
+        let (tx, _rx) = smol::channel::unbounded();
+        let mut under_test = GameLoop::new(4, 8, tx);
+        under_test.set_start_level(5);
+
+        run_state_machine(&mut under_test);
+
+        assert_eq!(5, under_test.level);
+    }
+
+    #[test]
+    fn level_goes_up_every_ten_lines() {
+        let (tx, rx) = smol::channel::unbounded();
+        let mut under_test = GameLoop::new(4, 8, tx).with_line_clear_ticks(0);
+        under_test.set_start_level(2);
+        run_state_machine(&mut under_test);
+        under_test.current_lines = 8;
+
+        fill_rows(&mut under_test, 2);
+        under_test.game_state = GameLoopState::CheckRows;
+        run_state_machine(&mut under_test);
+
+        assert_eq!(10, under_test.current_lines);
+        assert_eq!(3, under_test.level);
+        let levels: Vec<u16> = std::iter::from_fn(|| rx.try_recv().ok())
+            .filter_map(|message| match message {
+                GlobalStateManagementMessage::UpdateLevel(level) => Some(level),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(vec![2, 3], levels);
+    }
+
+    #[test]
+    fn start_level_is_clamped() {
+        let (tx, _rx) = smol::channel::unbounded();
+        let mut under_test = GameLoop::new(4, 8, tx);
+        under_test.set_start_level(40);
+
+        assert_eq!(19, under_test.start_level);
+    }
+
+    #[test]
+    fn fall_interval_follows_level() {
+        let (tx, _rx) = smol::channel::unbounded();
+        let mut under_test = GameLoop::new(4, 8, tx);
+        let slowest = under_test.fall_interval();
+
+        under_test.level = 10;
+
+        assert!(under_test.fall_interval() < slowest);
+    }
+
+    #[test]
+    fn line_clear_scored_by_scoring_system() {
+        let (tx, _rx) = smol::channel::unbounded();
+        let mut under_test = GameLoop::new(4, 8, tx)
+            .with_line_clear_ticks(0)
+            .with_scoring_system(ScoringSystemType::Nes);
+        run_state_machine(&mut under_test);
+        under_test.level = 3;
+
+        fill_rows(&mut under_test, 4);
+        under_test.game_state = GameLoopState::CheckRows;
+        let mut reported_score = 0;
+        under_test.do_state_machine(|score| reported_score = score, |_| (), |_| (), |_| ());
+
+        assert_eq!(4800, under_test.current_score);
+        assert_eq!(4800, reported_score);
+    }
+
+    #[test]
+    fn nes_hard_drop_is_not_scored() {
+        let (tx, _rx) = smol::channel::unbounded();
+        let mut under_test = GameLoop::new(4, 6, tx).with_scoring_system(ScoringSystemType::Nes);
+        under_test.piece = Tetronimo::new(TetronimoShape::OShape);
+        under_test.position = Position::new(1, 0);
+        under_test.game_state = GameLoopState::Running;
+
+        under_test.handle_input(GameAction::Move(MoveActionType::Drop));
+        run_state_machine(&mut under_test);
+
+        assert_eq!(0, under_test.current_score);
+    }
+
+    #[test]
+    fn score_does_not_overflow_u16() {
+        let (tx, _rx) = smol::channel::unbounded();
+        let mut under_test = GameLoop::new(4, 8, tx).with_line_clear_ticks(0);
+        run_state_machine(&mut under_test);
+        under_test.current_score = u16::MAX as u32;
+        under_test.level = 9;
+
+        fill_rows(&mut under_test, 4);
+        under_test.game_state = GameLoopState::CheckRows;
+        run_state_machine(&mut under_test);
+
+        assert_eq!(u16::MAX as u32 + 8000, under_test.current_score);
+    }
+
+    fn b_type(garbage_height: u16) -> GameSettings {
+        GameSettings {
+            mode: GameMode::BType,
+            start_level: 0,
+            garbage_height,
+        }
+    }
+
+    #[test]
+    fn b_type_starts_with_garbage_below_the_height() {
+        let (tx, _rx) = smol::channel::unbounded();
+        let mut under_test = GameLoop::new(10, 20, tx).with_seed(Some(3));
+        under_test.apply_settings(b_type(5));
+
+        run_state_machine(&mut under_test);
+
+        let rows: Vec<&[Option<TetronimoShape>]> = under_test.arena.chunks(10).collect();
+        assert!(rows[..8].iter().flat_map(|row| row.iter()).all(Option::is_none));
+        for row in &rows[8..] {
+            assert!(row.iter().any(Option::is_none));
+        }
+        assert!(rows[8..].iter().flat_map(|row| row.iter()).any(Option::is_some));
+    }
+
+    #[test]
+    fn b_type_without_height_starts_empty() {
+        let (tx, _rx) = smol::channel::unbounded();
+        let mut under_test = GameLoop::new(10, 20, tx);
+        under_test.apply_settings(b_type(0));
+
+        run_state_machine(&mut under_test);
+
+        assert!(under_test.arena.iter().all(Option::is_none));
+    }
+
+    #[test]
+    fn b_type_counts_lines_down() {
+        let (tx, _rx) = smol::channel::unbounded();
+        let mut under_test = GameLoop::new(4, 8, tx).with_line_clear_ticks(0);
+        under_test.apply_settings(b_type(0));
+        let mut counts = vec![];
+        under_test.do_state_machine(|_| (), |lines| counts.push(lines), |_| (), |_| ());
+
+        fill_rows(&mut under_test, 2);
+        under_test.game_state = GameLoopState::CheckRows;
+        under_test.do_state_machine(|_| (), |lines| counts.push(lines), |_| (), |_| ());
+
+        assert_eq!(vec![25, 23], counts);
+        assert_eq!(0, under_test.level);
+    }
+
+    #[test]
+    fn b_type_is_won_after_twenty_five_lines() {
+        let (tx, rx) = smol::channel::unbounded();
+        let mut under_test = GameLoop::new(4, 8, tx).with_line_clear_ticks(0);
+        under_test.apply_settings(b_type(0));
+        run_state_machine(&mut under_test);
+        under_test.current_lines = 23;
+
+        fill_rows(&mut under_test, 2);
+        under_test.game_state = GameLoopState::CheckRows;
+        run_state_machine(&mut under_test);
+        assert_eq!(GameLoopState::GameWon, under_test.game_state);
+
+        run_state_machine(&mut under_test);
+        assert_eq!(GameLoopState::Start, under_test.game_state);
+        let won = std::iter::from_fn(|| rx.try_recv().ok())
+            .any(|message| matches!(message, GlobalStateManagementMessage::GameWon));
+        assert!(won);
+    }
+
+    #[test]
+    fn garbage_height_is_clamped() {
+        let (tx, _rx) = smol::channel::unbounded();
+        let mut under_test = GameLoop::new(4, 8, tx);
+        under_test.apply_settings(b_type(9));
+
+        assert_eq!(MAX_GARBAGE_HEIGHT, under_test.garbage_height);
+    }
+
+    fn sprint() -> GameSettings {
+        GameSettings {
+            mode: GameMode::Sprint,
+            ..GameSettings::default()
+        }
+    }
+
+    #[test]
+    fn time_only_moves_during_a_game() {
+        let (tx, _rx) = smol::channel::unbounded();
+        let mut under_test = GameLoop::new(4, 8, tx);
+
+        under_test.advance_time(Duration::from_millis(500));
+        assert_eq!(Duration::ZERO, under_test.elapsed);
+
+        run_state_machine(&mut under_test);
+        under_test.advance_time(Duration::from_millis(16));
+        under_test.advance_time(Duration::from_millis(17));
+        assert_eq!(Duration::from_millis(33), under_test.elapsed);
+    }
+
+    #[test]
+    fn sprint_reports_time_while_playing() {
+        let (tx, rx) = smol::channel::unbounded();
+        let mut under_test = GameLoop::new(4, 8, tx);
+        under_test.apply_settings(sprint());
+        run_state_machine(&mut under_test);
+        while rx.try_recv().is_ok() {}
+
+        under_test.advance_time(Duration::from_millis(1234));
+
+        assert!(matches!(
+            rx.try_recv(),
+            Ok(GlobalStateManagementMessage::UpdateTime(time)) if time == Duration::from_millis(1234)
+        ));
+    }
+
+    #[test]
+    fn sprint_records_a_split_every_ten_lines() {
+        let (tx, _rx) = smol::channel::unbounded();
+        let mut under_test = GameLoop::new(4, 8, tx).with_line_clear_ticks(0);
+        under_test.apply_settings(sprint());
+        run_state_machine(&mut under_test);
+
+        under_test.current_lines = 8;
+        under_test.advance_time(Duration::from_secs(12));
+        fill_rows(&mut under_test, 3);
+        under_test.game_state = GameLoopState::CheckRows;
+        run_state_machine(&mut under_test);
+
+        assert_eq!(vec![Duration::from_secs(12)], under_test.splits);
+    }
+
+    #[test]
+    fn sprint_ends_after_forty_lines() {
+        let (tx, rx) = smol::channel::unbounded();
+        let mut under_test = GameLoop::new(4, 8, tx).with_line_clear_ticks(0);
+        under_test.apply_settings(sprint());
+        run_state_machine(&mut under_test);
+        under_test.current_lines = 38;
+        under_test.splits = vec![Duration::from_secs(10); 3];
+        under_test.advance_time(Duration::from_secs(42));
+
+        fill_rows(&mut under_test, 2);
+        under_test.game_state = GameLoopState::CheckRows;
+        run_state_machine(&mut under_test);
+        run_state_machine(&mut under_test);
+
+        let result = std::iter::from_fn(|| rx.try_recv().ok())
+            .find_map(|message| match message {
+                GlobalStateManagementMessage::GameResult(result) => Some(result),
+                _ => None,
+            })
+            .unwrap();
+        assert_eq!(Duration::from_secs(42), result.time);
+        assert_eq!(4, result.splits.len());
+        assert_eq!(0, under_test.level);
+    }
+
+    fn ultra() -> GameSettings {
+        GameSettings {
+            mode: GameMode::Ultra,
+            ..GameSettings::default()
+        }
+    }
+
+    #[test]
+    fn ultra_counts_down_the_time_left() {
+        let (tx, rx) = smol::channel::unbounded();
+        let mut under_test = GameLoop::new(4, 8, tx).with_ultra_duration(Duration::from_secs(10));
+        under_test.apply_settings(ultra());
+        run_state_machine(&mut under_test);
+        while rx.try_recv().is_ok() {}
+
+        under_test.advance_time(Duration::from_secs(4));
+
+        assert!(matches!(
+            rx.try_recv(),
+            Ok(GlobalStateManagementMessage::UpdateTime(time)) if time == Duration::from_secs(6)
+        ));
+    }
+
+    #[test]
+    fn ultra_ends_when_the_time_is_up() {
+        let (tx, rx) = smol::channel::unbounded();
+        let mut under_test = GameLoop::new(4, 8, tx).with_ultra_duration(Duration::from_secs(10));
+        under_test.apply_settings(ultra());
+        run_state_machine(&mut under_test);
+        under_test.current_score = 500;
+
+        under_test.advance_time(Duration::from_millis(9_999));
+        assert_eq!(GameLoopState::Running, under_test.game_state);
+
+        under_test.advance_time(Duration::from_millis(16));
+        assert_eq!(GameLoopState::GameWon, under_test.game_state);
+        run_state_machine(&mut under_test);
+
+        let result = std::iter::from_fn(|| rx.try_recv().ok())
+            .find_map(|message| match message {
+                GlobalStateManagementMessage::GameResult(result) => Some(result),
+                _ => None,
+            })
+            .unwrap();
+        assert_eq!(GameMode::Ultra, result.mode);
+        assert_eq!(500, result.score);
+        assert_eq!(Duration::from_secs(10), result.time);
+    }
+
+    #[test]
+    fn ultra_waits_for_rows_to_clear_before_ending() {
+        let (tx, _rx) = smol::channel::unbounded();
+        let mut under_test = GameLoop::new(4, 8, tx).with_ultra_duration(Duration::from_secs(10));
+        under_test.apply_settings(ultra());
+        run_state_machine(&mut under_test);
+
+        under_test.game_state = GameLoopState::CheckRows;
+        under_test.advance_time(Duration::from_secs(11));
+        assert_eq!(GameLoopState::CheckRows, under_test.game_state);
+
+        run_state_machine(&mut under_test);
+        run_state_machine(&mut under_test);
+        under_test.advance_time(Duration::from_millis(16));
+        assert_eq!(GameLoopState::GameWon, under_test.game_state);
+    }
 }
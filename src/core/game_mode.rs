@@ -0,0 +1,105 @@
+// The rules a game is played under, high scores are kept separately for each.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub(crate) enum GameMode {
+    #[default]
+    AType,
+    // Clear 25 lines, starting on top of a pile of garbage.
+    BType,
+    // Clear 40 lines as fast as possible.
+    Sprint,
+    // Score as much as possible before the time runs out.
+    Ultra,
+}
+
+impl GameMode {
+    pub(crate) const ALL: [GameMode; 4] = [
+        GameMode::AType,
+        GameMode::BType,
+        GameMode::Sprint,
+        GameMode::Ultra,
+    ];
+
+    // Name used for the mode in the high score file.
+    pub(crate) fn key(&self) -> &'static str {
+        match self {
+            GameMode::AType => "a-type",
+            GameMode::BType => "b-type",
+            GameMode::Sprint => "sprint",
+            GameMode::Ultra => "ultra",
+        }
+    }
+
+    pub(crate) fn label(&self) -> &'static str {
+        match self {
+            GameMode::AType => "A-TYPE",
+            GameMode::BType => "B-TYPE",
+            GameMode::Sprint => "SPRINT",
+            GameMode::Ultra => "ULTRA",
+        }
+    }
+
+    // Lines to clear to finish the game, endless modes don't have one.
+    pub(crate) fn line_goal(&self) -> Option<u16> {
+        match self {
+            GameMode::AType => None,
+            GameMode::BType => Some(25),
+            GameMode::Sprint => Some(40),
+            GameMode::Ultra => None,
+        }
+    }
+
+    // Timed modes show a clock while playing and are judged on time.
+    pub(crate) fn is_timed(&self) -> bool {
+        matches!(self, GameMode::Sprint | GameMode::Ultra)
+    }
+
+    // The mode after this one on the main menu, wrapping around at either end.
+    pub(crate) fn cycle(&self, change: isize) -> GameMode {
+        let count = GameMode::ALL.len() as isize;
+        let index = GameMode::ALL
+            .iter()
+            .position(|mode| mode == self)
+            .unwrap_or_default() as isize;
+        GameMode::ALL[(index + change).rem_euclid(count) as usize]
+    }
+}
+
+impl TryFrom<&str> for GameMode {
+    type Error = String;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        GameMode::ALL
+            .into_iter()
+            .find(|mode| mode.key() == value)
+            .ok_or_else(|| format!("unknown game mode '{value}'"))
+    }
+}
+
+// Everything picked on the main menu before a game starts.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub(crate) struct GameSettings {
+    pub(crate) mode: GameMode,
+    pub(crate) start_level: u16,
+    // Only used by B-Type games.
+    pub(crate) garbage_height: u16,
+}
+
+#[cfg(test)]
+mod test {
+    use super::GameMode;
+
+    #[test]
+    fn modes_cycle_in_both_directions() {
+        assert_eq!(GameMode::BType, GameMode::AType.cycle(1));
+        assert_eq!(GameMode::AType, GameMode::Ultra.cycle(1));
+        assert_eq!(GameMode::Ultra, GameMode::AType.cycle(-1));
+    }
+
+    #[test]
+    fn modes_parse_from_their_key() {
+        for mode in GameMode::ALL {
+            assert_eq!(Ok(mode), GameMode::try_from(mode.key()));
+        }
+        assert!(GameMode::try_from("c-type").is_err());
+    }
+}
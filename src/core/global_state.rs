@@ -1,20 +1,37 @@
-use std::process;
+use std::{path::PathBuf, process, time::Duration};
 
-use anathema::component::{ComponentId, Emitter, KeyCode, KeyEvent};
+use anathema::{
+    component::{ComponentId, Emitter, KeyCode, KeyEvent},
+    widgets::components::events::KeyState,
+};
 use smol::channel::{Receiver, Sender};
 
 use crate::widgets::{
+    callout::CalloutComponentMessage,
     game::GameComponentMessage,
     game_arena::GameArenaComponentMessage,
-    game_over::GameOverComponentMessage,
+    game_over::{GameOverComponentMessage, NameEntryAction},
+    game_type::GameTypeMessage,
+    hold::HoldComponentMessage,
+    level::LevelComponentMessage,
     line_count::LineCountComponentMessage,
     main_menu::{MainMenuAction, MainMenuComponentMessage},
     next_piece::NextPieceComponentMessage,
     scoreboard::ScoreBoardComponentMessage,
     statistics::StatisticsComponentMessage,
+    streaks::StreaksComponentMessage,
+    timer::TimerComponentMessage,
 };
 
-use super::tetronimo::TetronimoShape;
+use super::{
+    game_loop::GameResult,
+    game_mode::{GameMode, GameSettings},
+    high_scores::{self, HighScoreEntry, HighScores, SprintRecord},
+    keymap::{Keymap, MenuAction, PlayAction},
+    scoring::TSpin,
+    settings::{self, Settings},
+    tetronimo::TetronimoShape,
+};
 
 pub(crate) struct GameStateComponentIds {
     main_menu_id: ComponentId<MainMenuComponentMessage>,
@@ -23,8 +40,14 @@ pub(crate) struct GameStateComponentIds {
     game_over_id: ComponentId<GameOverComponentMessage>,
     score_board_id: ComponentId<ScoreBoardComponentMessage>,
     lines_count_id: ComponentId<LineCountComponentMessage>,
+    level_id: ComponentId<LevelComponentMessage>,
+    game_type_id: ComponentId<GameTypeMessage>,
     next_piece_id: ComponentId<NextPieceComponentMessage>,
+    hold_id: ComponentId<HoldComponentMessage>,
     statistics_id: ComponentId<StatisticsComponentMessage>,
+    timer_id: ComponentId<TimerComponentMessage>,
+    callout_id: ComponentId<CalloutComponentMessage>,
+    streaks_id: ComponentId<StreaksComponentMessage>,
 }
 
 #[allow(clippy::too_many_arguments)]
@@ -36,8 +59,14 @@ impl GameStateComponentIds {
         game_over_id: ComponentId<GameOverComponentMessage>,
         score_board_id: ComponentId<ScoreBoardComponentMessage>,
         lines_count_id: ComponentId<LineCountComponentMessage>,
+        level_id: ComponentId<LevelComponentMessage>,
+        game_type_id: ComponentId<GameTypeMessage>,
         next_piece_id: ComponentId<NextPieceComponentMessage>,
+        hold_id: ComponentId<HoldComponentMessage>,
         statistics_id: ComponentId<StatisticsComponentMessage>,
+        timer_id: ComponentId<TimerComponentMessage>,
+        callout_id: ComponentId<CalloutComponentMessage>,
+        streaks_id: ComponentId<StreaksComponentMessage>,
     ) -> Self {
         Self {
             main_menu_id,
@@ -46,8 +75,14 @@ impl GameStateComponentIds {
             game_over_id,
             score_board_id,
             lines_count_id,
+            level_id,
+            game_type_id,
             next_piece_id,
+            hold_id,
             statistics_id,
+            timer_id,
+            callout_id,
+            streaks_id,
         }
     }
 }
@@ -57,9 +92,14 @@ pub(crate) fn start(
     tx: Sender<GlobalStateManagementMessage>,
     rx: Receiver<GlobalStateManagementMessage>,
     game_state_component_ids: GameStateComponentIds,
+    mut high_scores: HighScores,
+    keymap: Keymap,
+    settings_path: Option<PathBuf>,
 ) {
     smol::spawn(async move {
         let mut state = GameState::MainMenu;
+        // A game that made the high scores, waiting for the player's initials.
+        let mut pending_result: Option<GameResult> = None;
 
         while let Ok(message) = rx.recv().await {
             match message {
@@ -135,15 +175,43 @@ pub(crate) fn start(
                     );
                     state = message.into();
                 }
+                GlobalStateManagementMessage::GameWon => {
+                    let _ = emitter.emit(
+                        game_state_component_ids.main_menu_id,
+                        MainMenuComponentMessage::Invisible,
+                    );
+                    let _ = emitter.emit(
+                        game_state_component_ids.game_id,
+                        GameComponentMessage::Invisible,
+                    );
+                    let _ = emitter.emit(
+                        game_state_component_ids.game_over_id,
+                        GameOverComponentMessage::Visible,
+                    );
+                    let _ = emitter.emit(
+                        game_state_component_ids.game_over_id,
+                        GameOverComponentMessage::Won,
+                    );
+                    state = message.into();
+                }
                 GlobalStateManagementMessage::Event(event) => match state {
-                    GameState::MainMenu => {
-                        handle_main_menu(game_state_component_ids.main_menu_id, event, &emitter)
-                    }
-                    GameState::Paused => handle_pause(event, &tx),
-                    GameState::Playing => {
-                        handle_playing(event, &tx, &emitter, game_state_component_ids.game_arena_id)
+                    GameState::MainMenu => handle_main_menu(
+                        game_state_component_ids.main_menu_id,
+                        event,
+                        &emitter,
+                        &keymap,
+                    ),
+                    GameState::Paused => handle_pause(event, &tx, &keymap),
+                    GameState::Playing => handle_playing(
+                        event,
+                        &tx,
+                        &emitter,
+                        game_state_component_ids.game_arena_id,
+                        &keymap,
+                    ),
+                    GameState::GameOver => {
+                        handle_game_over(event, &emitter, game_state_component_ids.game_over_id)
                     }
-                    GameState::GameOver => handle_game_over(),
                 },
                 GlobalStateManagementMessage::UpdateScore(score) => {
                     handle_update_score(&emitter, score, game_state_component_ids.score_board_id)
@@ -151,16 +219,128 @@ pub(crate) fn start(
                 GlobalStateManagementMessage::UpdateLines(value) => {
                     handle_update_lines(&emitter, value, game_state_component_ids.lines_count_id)
                 }
-                GlobalStateManagementMessage::UpdateNextTetronimo(tetronimo) => {
-                    handle_update_next_tetronimo(
+                GlobalStateManagementMessage::UpdateLevel(value) => {
+                    handle_update_level(&emitter, value, game_state_component_ids.level_id)
+                }
+                GlobalStateManagementMessage::NewGame(settings) => {
+                    handle_update_top_score(
+                        &emitter,
+                        high_scores.top_score(settings.mode),
+                        game_state_component_ids.score_board_id,
+                    );
+                    let _ = emitter.emit(
+                        game_state_component_ids.game_type_id,
+                        GameTypeMessage::Mode(settings.mode),
+                    );
+                    handle_new_game_timer(
+                        &emitter,
+                        &high_scores,
+                        settings.mode,
+                        game_state_component_ids.timer_id,
+                    );
+                    let _ = emitter.emit(
+                        game_state_component_ids.game_arena_id,
+                        GameArenaComponentMessage::NewGame(settings),
+                    );
+                }
+                GlobalStateManagementMessage::UpdateSettings(new_settings, saved_settings) => {
+                    if let Err(error) = settings::save(settings_path.as_deref(), &saved_settings) {
+                        let _ = emitter.emit(
+                            game_state_component_ids.main_menu_id,
+                            MainMenuComponentMessage::SaveFailed(error.to_string()),
+                        );
+                    }
+                    let _ = emitter.emit(
+                        game_state_component_ids.game_arena_id,
+                        GameArenaComponentMessage::ApplySettings(new_settings),
+                    );
+                    let _ = emitter.emit(
+                        game_state_component_ids.game_id,
+                        GameComponentMessage::Theme(new_settings.theme),
+                    );
+                }
+                GlobalStateManagementMessage::UpdateNextTetronimoQueue(tetronimos) => {
+                    handle_update_next_tetronimo_queue(
                         &emitter,
                         game_state_component_ids.next_piece_id,
+                        tetronimos,
+                    )
+                }
+                GlobalStateManagementMessage::UpdateHoldTetronimo(tetronimo, locked) => {
+                    handle_update_hold_tetronimo(
+                        &emitter,
+                        game_state_component_ids.hold_id,
                         tetronimo,
+                        locked,
                     )
                 }
                 GlobalStateManagementMessage::UpdateStatistics(data) => {
                     handle_update_statistics(&emitter, game_state_component_ids.statistics_id, data)
                 }
+                GlobalStateManagementMessage::UpdateSeed(seed) => {
+                    handle_update_seed(&emitter, seed, game_state_component_ids.game_over_id)
+                }
+                GlobalStateManagementMessage::UpdateTime(time) => {
+                    let _ = emitter.emit(
+                        game_state_component_ids.timer_id,
+                        TimerComponentMessage::Time(time),
+                    );
+                }
+                GlobalStateManagementMessage::TSpin(t_spin, lines) => {
+                    let _ = emitter.emit(
+                        game_state_component_ids.callout_id,
+                        CalloutComponentMessage::TSpin(t_spin, lines),
+                    );
+                }
+                GlobalStateManagementMessage::UpdateCombo(combo) => {
+                    let _ = emitter.emit(
+                        game_state_component_ids.streaks_id,
+                        StreaksComponentMessage::Combo(combo),
+                    );
+                }
+                GlobalStateManagementMessage::UpdateBackToBack(streak) => {
+                    let _ = emitter.emit(
+                        game_state_component_ids.streaks_id,
+                        StreaksComponentMessage::BackToBack(streak),
+                    );
+                }
+                GlobalStateManagementMessage::GameResult(result) => match result.mode {
+                    GameMode::Sprint => handle_sprint_result(
+                        &emitter,
+                        &mut high_scores,
+                        result,
+                        game_state_component_ids.game_over_id,
+                    ),
+                    _ => {
+                        let _ = emitter.emit(
+                            game_state_component_ids.game_over_id,
+                            GameOverComponentMessage::Score(result.score),
+                        );
+                        if high_scores.qualifies(result.mode, result.score) {
+                            pending_result = Some(result);
+                            let _ = emitter.emit(
+                                game_state_component_ids.game_over_id,
+                                GameOverComponentMessage::EnterInitials,
+                            );
+                        }
+                    }
+                },
+                GlobalStateManagementMessage::SubmitInitials(initials) => {
+                    if let Some(result) = pending_result.take() {
+                        handle_submit_initials(
+                            &emitter,
+                            &mut high_scores,
+                            &result,
+                            &initials,
+                            game_state_component_ids.game_over_id,
+                        );
+                        handle_update_top_score(
+                            &emitter,
+                            high_scores.top_score(result.mode),
+                            game_state_component_ids.score_board_id,
+                        );
+                    }
+                }
                 GlobalStateManagementMessage::Exit => {
                     process::exit(0);
                 }
@@ -170,6 +350,98 @@ pub(crate) fn start(
     .detach();
 }
 
+fn handle_new_game_timer(
+    emitter: &Emitter,
+    high_scores: &HighScores,
+    mode: GameMode,
+    timer_id: ComponentId<TimerComponentMessage>,
+) {
+    let best = match mode {
+        GameMode::Sprint => high_scores.sprint_best().map(|record| record.time),
+        _ => None,
+    };
+    let _ = emitter.emit(timer_id, TimerComponentMessage::Show(mode.is_timed()));
+    let _ = emitter.emit(timer_id, TimerComponentMessage::Best(best));
+}
+
+// Only sprints that reached the line goal can become the personal best.
+fn handle_sprint_result(
+    emitter: &Emitter,
+    high_scores: &mut HighScores,
+    result: GameResult,
+    game_over_id: ComponentId<GameOverComponentMessage>,
+) {
+    let finished = result
+        .mode
+        .line_goal()
+        .is_some_and(|goal| result.lines >= goal);
+    let personal_best = finished
+        && high_scores.record_sprint(SprintRecord {
+            time: result.time,
+            splits: result.splits.clone(),
+            date: high_scores::today(),
+        });
+
+    let _ = emitter.emit(
+        game_over_id,
+        GameOverComponentMessage::Time(result.time, result.splits, personal_best),
+    );
+    if personal_best {
+        handle_save_high_scores(emitter, high_scores, game_over_id);
+    }
+}
+
+fn handle_submit_initials(
+    emitter: &Emitter,
+    high_scores: &mut HighScores,
+    result: &GameResult,
+    initials: &str,
+    game_over_id: ComponentId<GameOverComponentMessage>,
+) {
+    high_scores.insert(
+        result.mode,
+        HighScoreEntry {
+            score: result.score,
+            lines: result.lines,
+            level: result.level,
+            date: high_scores::today(),
+            initials: high_scores::sanitise_initials(initials),
+        },
+    );
+    handle_save_high_scores(emitter, high_scores, game_over_id);
+}
+
+// A record that can't be written is still kept for the rest of the session,
+// the game over screen says it won't be there after a restart.
+fn handle_save_high_scores(
+    emitter: &Emitter,
+    high_scores: &HighScores,
+    game_over_id: ComponentId<GameOverComponentMessage>,
+) {
+    if let Err(error) = high_scores.save() {
+        let _ = emitter.emit(
+            game_over_id,
+            GameOverComponentMessage::SaveFailed(error.to_string()),
+        );
+    }
+}
+
+fn handle_update_top_score(
+    emitter: &Emitter,
+    score: u32,
+    score_board_id: ComponentId<ScoreBoardComponentMessage>,
+) {
+    let _ = emitter.emit(score_board_id, ScoreBoardComponentMessage::Top(score));
+}
+
+fn handle_update_seed(
+    emitter: &Emitter,
+    seed: u64,
+    game_over_id: ComponentId<GameOverComponentMessage>,
+) {
+    let _ = emitter.emit(game_over_id, GameOverComponentMessage::Seed(seed));
+}
+
 fn handle_update_statistics(
     emitter: &Emitter,
     statistics_id: ComponentId<StatisticsComponentMessage>,
@@ -178,12 +450,21 @@ fn handle_update_statistics(
     let _ = emitter.emit(statistics_id, data);
 }
 
-fn handle_update_next_tetronimo(
+fn handle_update_next_tetronimo_queue(
     emitter: &Emitter,
     next_piece_id: ComponentId<NextPieceComponentMessage>,
-    tetronimo: TetronimoShape,
+    tetronimos: Vec<TetronimoShape>,
+) {
+    let _ = emitter.emit(next_piece_id, NextPieceComponentMessage::new(tetronimos));
+}
+
+fn handle_update_hold_tetronimo(
+    emitter: &Emitter,
+    hold_id: ComponentId<HoldComponentMessage>,
+    tetronimo: Option<TetronimoShape>,
+    locked: bool,
 ) {
-    let _ = emitter.emit(next_piece_id, NextPieceComponentMessage::new(tetronimo));
+    let _ = emitter.emit(hold_id, HoldComponentMessage::new(tetronimo, locked));
 }
 
 fn handle_update_lines(
@@ -194,9 +475,17 @@ fn handle_update_lines(
     let _ = emitter.emit(lines_count_id, LineCountComponentMessage::Count(value));
 }
 
+fn handle_update_level(
+    emitter: &Emitter,
+    value: u16,
+    level_id: ComponentId<LevelComponentMessage>,
+) {
+    let _ = emitter.emit(level_id, LevelComponentMessage::Level(value));
+}
+
 fn handle_update_score(
     emitter: &Emitter,
-    score: u16,
+    score: u32,
     score_board_id: ComponentId<ScoreBoardComponentMessage>,
 ) {
     let _ = emitter.emit(score_board_id, ScoreBoardComponentMessage::Score(score));
@@ -206,42 +495,26 @@ fn handle_main_menu(
     main_menu_id: ComponentId<MainMenuComponentMessage>,
     event: anathema::component::Event,
     tx: &Emitter,
+    keymap: &Keymap,
 ) {
     if let anathema::component::Event::Key(keyevent) = event {
-        match keyevent {
-            KeyEvent {
-                code: KeyCode::Enter,
-                ..
-            } => {
-                let _ = tx.emit(
-                    main_menu_id,
-                    MainMenuComponentMessage::Change(MainMenuAction::Enter),
-                );
-            }
-            KeyEvent {
-                code: KeyCode::Char('w'),
-                ..
-            } => {
-                let _ = tx.emit(
-                    main_menu_id,
-                    MainMenuComponentMessage::Change(MainMenuAction::Up),
-                );
-            }
-            KeyEvent {
-                code: KeyCode::Char('s'),
-                ..
-            } => {
-                let _ = tx.emit(
-                    main_menu_id,
-                    MainMenuComponentMessage::Change(MainMenuAction::Down),
-                );
-            }
-            _ => (),
-        }
+        let action = match keymap.menu_action(&keyevent.code) {
+            Some(MenuAction::Up) => MainMenuAction::Up,
+            Some(MenuAction::Down) => MainMenuAction::Down,
+            Some(MenuAction::Left) => MainMenuAction::Left,
+            Some(MenuAction::Right) => MainMenuAction::Right,
+            Some(MenuAction::Select) => MainMenuAction::Enter,
+            None => return,
+        };
+        let _ = tx.emit(main_menu_id, MainMenuComponentMessage::Change(action));
     }
 }
 
-fn handle_pause(event: anathema::component::Event, tx: &Sender<GlobalStateManagementMessage>) {
+fn handle_pause(
+    event: anathema::component::Event,
+    tx: &Sender<GlobalStateManagementMessage>,
+    keymap: &Keymap,
+) {
     if let anathema::component::Event::Key(key_event) = event {
         let KeyEvent {
             code,
@@ -249,9 +522,9 @@ fn handle_pause(event: anathema::component::Event, tx: &Sender<GlobalStateManage
             state: _,
         } = key_event;
 
-        if let KeyCode::Esc = code {
+        if let Some(PlayAction::Pause) = keymap.play_action(&code) {
             let _ = tx.try_send(GlobalStateManagementMessage::Playing);
-        } else if let KeyCode::Enter = code {
+        } else if let Some(MenuAction::Select) = keymap.menu_action(&code) {
             let _ = tx.try_send(GlobalStateManagementMessage::MainMenu);
         }
     }
@@ -262,36 +535,71 @@ fn handle_playing(
     tx: &Sender<GlobalStateManagementMessage>,
     emitter: &Emitter,
     game_arena: ComponentId<GameArenaComponentMessage>,
+    keymap: &Keymap,
 ) {
     if let anathema::component::Event::Key(keyevent) = event {
         let KeyEvent {
             code,
             ctrl: _,
-            state: _,
+            state,
         } = keyevent;
+        let Some(action) = keymap.play_action(&code) else {
+            return;
+        };
+
+        if let KeyState::Release = state {
+            let released = match action {
+                PlayAction::SoftDrop => GameArenaComponentMessage::SoftDropReleased,
+                PlayAction::MoveLeft => GameArenaComponentMessage::MoveLeftReleased,
+                PlayAction::MoveRight => GameArenaComponentMessage::MoveRightReleased,
+                _ => return,
+            };
+            let _ = emitter.emit(game_arena, released);
+            return;
+        }
 
-        match code {
-            KeyCode::Esc => {
+        let message = match action {
+            PlayAction::Pause => {
                 let _ = tx.try_send(GlobalStateManagementMessage::Paused);
+                return;
             }
-            KeyCode::Char(' ') => {
-                let _ = emitter.emit(game_arena, GameArenaComponentMessage::Rotate);
-            }
-            KeyCode::Char('a') => {
-                let _ = emitter.emit(game_arena, GameArenaComponentMessage::MoveLeft);
-            }
-            KeyCode::Char('d') => {
-                let _ = emitter.emit(game_arena, GameArenaComponentMessage::MoveRight);
-            }
-            KeyCode::Char('s') => {
-                let _ = emitter.emit(game_arena, GameArenaComponentMessage::Drop);
-            }
-            _ => (),
-        }
+            PlayAction::RotateClockwise => GameArenaComponentMessage::RotateClockwise,
+            PlayAction::RotateCounterClockwise => GameArenaComponentMessage::RotateCounterClockwise,
+            PlayAction::MoveLeft => GameArenaComponentMessage::MoveLeft,
+            PlayAction::MoveRight => GameArenaComponentMessage::MoveRight,
+            PlayAction::SoftDrop => GameArenaComponentMessage::SoftDrop,
+            PlayAction::HardDrop => GameArenaComponentMessage::Drop,
+            PlayAction::Hold => GameArenaComponentMessage::Hold,
+        };
+        let _ = emitter.emit(game_arena, message);
     }
 }
 
-fn handle_game_over() {}
+fn handle_game_over(
+    event: anathema::component::Event,
+    emitter: &Emitter,
+    game_over_id: ComponentId<GameOverComponentMessage>,
+) {
+    if let anathema::component::Event::Key(keyevent) = event {
+        let KeyEvent {
+            code,
+            ctrl: _,
+            state,
+        } = keyevent;
+
+        if let KeyState::Release = state {
+            return;
+        }
+
+        let action = match code {
+            KeyCode::Char(character) => NameEntryAction::Char(character),
+            KeyCode::Backspace => NameEntryAction::Backspace,
+            KeyCode::Enter => NameEntryAction::Confirm,
+            _ => return,
+        };
+        let _ = emitter.emit(game_over_id, GameOverComponentMessage::NameEntry(action));
+    }
+}
 
 #[derive(Debug)]
 pub(crate) enum GlobalStateManagementMessage {
@@ -299,11 +607,24 @@ pub(crate) enum GlobalStateManagementMessage {
     Paused,
     Playing,
     GameOver,
+    GameWon,
+    NewGame(GameSettings),
+    // The settings in play and the ones to save.
+    UpdateSettings(Settings, Settings),
     Event(anathema::component::Event),
-    UpdateScore(u16),
+    UpdateScore(u32),
     UpdateLines(u16),
-    UpdateNextTetronimo(TetronimoShape),
+    UpdateLevel(u16),
+    UpdateNextTetronimoQueue(Vec<TetronimoShape>),
+    UpdateHoldTetronimo(Option<TetronimoShape>, bool),
     UpdateStatistics(StatisticsComponentMessage),
+    UpdateSeed(u64),
+    UpdateTime(Duration),
+    TSpin(TSpin, u16),
+    UpdateCombo(u16),
+    UpdateBackToBack(u16),
+    GameResult(GameResult),
+    SubmitInitials(String),
     Exit,
 }
 
@@ -322,6 +643,7 @@ impl From<GlobalStateManagementMessage> for GameState {
             GlobalStateManagementMessage::Paused => GameState::Paused,
             GlobalStateManagementMessage::Playing => GameState::Playing,
             GlobalStateManagementMessage::GameOver => GameState::GameOver,
+            GlobalStateManagementMessage::GameWon => GameState::GameOver,
             _ => {
                 panic!("Key handling state is not a valid state to transition to")
             }
@@ -0,0 +1,138 @@
+use std::time::Duration;
+
+const NES_FRAMES_PER_SECOND: f64 = 60.0988;
+const GUIDELINE_FRAMES_PER_SECOND: f64 = 60.0;
+
+// Frames it takes a piece to fall a single row on the NES, the last entry is
+// used for every level after it.
+const NES_FRAMES_PER_ROW: [u16; 30] = [
+    48, 43, 38, 33, 28, 23, 18, 13, 8, 6, // 0 - 9
+    5, 5, 5, 4, 4, 4, 3, 3, 3, 2, // 10 - 19
+    2, 2, 2, 2, 2, 2, 2, 2, 2, 1, // 20 - 29
+];
+
+// Decides how quickly pieces fall for a given level.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub(crate) enum GravityCurve {
+    #[default]
+    Nes,
+    Guideline,
+}
+
+impl GravityCurve {
+    pub(crate) fn frames_per_row(&self, level: u16) -> f64 {
+        match self {
+            GravityCurve::Nes => {
+                let index = (level as usize).min(NES_FRAMES_PER_ROW.len() - 1);
+                NES_FRAMES_PER_ROW[index] as f64
+            }
+            GravityCurve::Guideline => {
+                // The guideline counts levels from one and gives the time per
+                // row in seconds as (0.8 - ((level - 1) * 0.007))^(level - 1).
+                let level = level.min(19) as f64;
+                let seconds = (0.8 - (level * 0.007)).powf(level);
+                seconds * GUIDELINE_FRAMES_PER_SECOND
+            }
+        }
+    }
+
+    pub(crate) fn row_duration(&self, level: u16) -> Duration {
+        let frames_per_second = match self {
+            GravityCurve::Nes => NES_FRAMES_PER_SECOND,
+            GravityCurve::Guideline => GUIDELINE_FRAMES_PER_SECOND,
+        };
+        Duration::from_secs_f64(self.frames_per_row(level) / frames_per_second)
+    }
+}
+
+impl TryFrom<&str> for GravityCurve {
+    type Error = String;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value {
+            "nes" => Ok(GravityCurve::Nes),
+            "guideline" => Ok(GravityCurve::Guideline),
+            _ => Err(format!(
+                "unknown gravity curve '{value}', expected one of: nes, guideline"
+            )),
+        }
+    }
+}
+
+// What happens to the blocks above a cleared row. Naive gravity moves the
+// rows above down as they are, cascade lets every block fall on its own
+// until it lands, which can complete more rows.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub(crate) enum LineClearGravity {
+    #[default]
+    Naive,
+    Cascade,
+}
+
+impl TryFrom<&str> for LineClearGravity {
+    type Error = String;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value {
+            "naive" => Ok(LineClearGravity::Naive),
+            "cascade" => Ok(LineClearGravity::Cascade),
+            _ => Err(format!(
+                "unknown line clear gravity '{value}', expected one of: naive, cascade"
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use super::GravityCurve;
+
+    #[test]
+    fn nes_frames_per_row() {
+        let cases = [
+            (0, 48.0),
+            (1, 43.0),
+            (8, 8.0),
+            (9, 6.0),
+            (10, 5.0),
+            (13, 4.0),
+            (16, 3.0),
+            (19, 2.0),
+            (28, 2.0),
+            (29, 1.0),
+            (99, 1.0),
+        ];
+
+        for (level, frames) in cases {
+            assert_eq!(frames, GravityCurve::Nes.frames_per_row(level), "level {level}");
+        }
+    }
+
+    #[test]
+    fn guideline_frames_per_row() {
+        let cases = [(0, 60.0), (1, 47.58), (4, 21.31), (9, 3.85), (14, 0.42), (19, 0.03)];
+
+        for (level, frames) in cases {
+            let actual = GravityCurve::Guideline.frames_per_row(level);
+            assert!((frames - actual).abs() < 0.01, "level {level} was {actual}");
+        }
+    }
+
+    #[test]
+    fn gravity_never_slows_down() {
+        for curve in [GravityCurve::Nes, GravityCurve::Guideline] {
+            for level in 1..40 {
+                assert!(curve.frames_per_row(level) <= curve.frames_per_row(level - 1));
+            }
+        }
+    }
+
+    #[test]
+    fn row_duration_uses_frame_rate() {
+        assert_eq!(Duration::from_secs(1), GravityCurve::Guideline.row_duration(0));
+        let nes = GravityCurve::Nes.row_duration(0);
+        assert!(nes > Duration::from_millis(798) && nes < Duration::from_millis(800));
+    }
+}
@@ -0,0 +1,143 @@
+use std::time::Duration;
+
+// A little longer than the delay most terminals wait before repeating a key.
+pub(crate) const DEFAULT_KEY_HOLD: Duration = Duration::from_millis(600);
+// Once they do repeat it the presses come every 30 to 50ms.
+const REPEAT_GAP: Duration = Duration::from_millis(150);
+
+// Most terminals never say when a key is let go, they only keep repeating the
+// press while it is held down. A key counts as held until no press has come
+// for the hold time, or for a much shorter gap once the repeats have started.
+// After the first release from the terminal the key is simply held until the
+// next release.
+pub(crate) struct HeldKey {
+    hold: Duration,
+    held: bool,
+    repeating: bool,
+    releases_reported: bool,
+    since_press: Duration,
+}
+
+impl HeldKey {
+    pub(crate) fn new(hold: Duration) -> Self {
+        Self {
+            hold,
+            held: false,
+            repeating: false,
+            releases_reported: false,
+            since_press: Duration::ZERO,
+        }
+    }
+
+    // Returns true for a new press, false for the terminal repeating it.
+    pub(crate) fn press(&mut self) -> bool {
+        self.since_press = Duration::ZERO;
+        if self.held {
+            self.repeating = true;
+            return false;
+        }
+
+        self.held = true;
+        true
+    }
+
+    pub(crate) fn release(&mut self) {
+        self.releases_reported = true;
+        self.reset();
+    }
+
+    // Lets go of the key without the terminal having reported it.
+    pub(crate) fn reset(&mut self) {
+        self.held = false;
+        self.repeating = false;
+    }
+
+    pub(crate) fn is_held(&self) -> bool {
+        self.held
+    }
+
+    // A tap and the start of a hold look the same until the terminal repeats
+    // the key, unless it reports releases.
+    pub(crate) fn is_held_down(&self) -> bool {
+        self.held && (self.repeating || self.releases_reported)
+    }
+
+    pub(crate) fn tick(&mut self, dt: Duration) {
+        if !self.held || self.releases_reported {
+            return;
+        }
+
+        self.since_press += dt;
+        let timeout = match self.repeating {
+            true => REPEAT_GAP,
+            false => self.hold,
+        };
+        if self.since_press >= timeout {
+            self.reset();
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use super::{HeldKey, REPEAT_GAP};
+
+    fn millis(value: u64) -> Duration {
+        Duration::from_millis(value)
+    }
+
+    #[test]
+    fn a_tap_is_let_go_after_the_hold_time() {
+        let mut under_test = HeldKey::new(millis(500));
+        assert!(under_test.press());
+
+        under_test.tick(millis(490));
+        assert!(under_test.is_held());
+        assert!(!under_test.is_held_down());
+
+        under_test.tick(millis(10));
+        assert!(!under_test.is_held());
+    }
+
+    #[test]
+    fn repeats_keep_the_key_held_down() {
+        let mut under_test = HeldKey::new(millis(500));
+        under_test.press();
+        under_test.tick(millis(450));
+
+        assert!(!under_test.press());
+        assert!(under_test.is_held_down());
+
+        under_test.tick(millis(100));
+        assert!(!under_test.press());
+        assert!(under_test.is_held_down());
+    }
+
+    #[test]
+    fn the_key_is_let_go_soon_after_the_repeats_stop() {
+        let mut under_test = HeldKey::new(millis(500));
+        under_test.press();
+        under_test.press();
+
+        under_test.tick(REPEAT_GAP);
+
+        assert!(!under_test.is_held());
+    }
+
+    #[test]
+    fn once_releases_are_reported_a_press_lasts_until_its_release() {
+        let mut under_test = HeldKey::new(millis(500));
+        under_test.press();
+        under_test.release();
+
+        assert!(under_test.press());
+        assert!(under_test.is_held_down());
+        under_test.tick(millis(2000));
+        assert!(under_test.is_held_down());
+
+        under_test.release();
+        assert!(!under_test.is_held());
+    }
+}
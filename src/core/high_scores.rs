@@ -0,0 +1,428 @@
+use std::{
+    collections::HashMap,
+    env, fs, io,
+    path::PathBuf,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use super::game_mode::GameMode;
+
+pub(crate) const MAX_ENTRIES: usize = 10;
+pub(crate) const MAX_INITIALS: usize = 3;
+
+const DATA_DIRECTORY: &str = "anatris";
+const FILE_NAME: &str = "high_scores";
+const SECONDS_PER_DAY: u64 = 86_400;
+const SPRINT_BEST_KEY: &str = "sprint-best";
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct HighScoreEntry {
+    pub(crate) score: u32,
+    pub(crate) lines: u16,
+    pub(crate) level: u16,
+    pub(crate) date: String,
+    pub(crate) initials: String,
+}
+
+// The fastest finished sprint, with the time taken at every split.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct SprintRecord {
+    pub(crate) time: Duration,
+    pub(crate) splits: Vec<Duration>,
+    pub(crate) date: String,
+}
+
+// The best games played for every mode, kept as a plain text file with one
+// tab separated entry per line so it survives between runs.
+pub(crate) struct HighScores {
+    path: Option<PathBuf>,
+    entries: HashMap<GameMode, Vec<HighScoreEntry>>,
+    sprint_best: Option<SprintRecord>,
+}
+
+impl HighScores {
+    // A missing or unreadable file just means there are no high scores yet.
+    pub(crate) fn load(path: Option<PathBuf>) -> Self {
+        let contents = path
+            .as_ref()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .unwrap_or_default();
+
+        Self {
+            path,
+            ..parse(&contents)
+        }
+    }
+
+    pub(crate) fn save(&self) -> io::Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+
+        if let Some(directory) = path.parent() {
+            fs::create_dir_all(directory)?;
+        }
+        fs::write(path, self.serialise())
+    }
+
+    pub(crate) fn entries(&self, mode: GameMode) -> &[HighScoreEntry] {
+        self.entries.get(&mode).map_or(&[], Vec::as_slice)
+    }
+
+    pub(crate) fn top_score(&self, mode: GameMode) -> u32 {
+        self.entries(mode).first().map_or(0, |entry| entry.score)
+    }
+
+    pub(crate) fn qualifies(&self, mode: GameMode, score: u32) -> bool {
+        let entries = self.entries(mode);
+        score > 0
+            && (entries.len() < MAX_ENTRIES
+                || entries.last().is_some_and(|entry| score > entry.score))
+    }
+
+    // Equal scores are placed after the ones that were already there, the
+    // first player to reach a score keeps the higher spot.
+    pub(crate) fn insert(&mut self, mode: GameMode, entry: HighScoreEntry) {
+        let entries = self.entries.entry(mode).or_default();
+        let index = entries
+            .iter()
+            .position(|existing| existing.score < entry.score)
+            .unwrap_or(entries.len());
+        entries.insert(index, entry);
+        entries.truncate(MAX_ENTRIES);
+    }
+
+    pub(crate) fn sprint_best(&self) -> Option<&SprintRecord> {
+        self.sprint_best.as_ref()
+    }
+
+    // Keeps the record if it beats the current best, returns whether it did.
+    pub(crate) fn record_sprint(&mut self, record: SprintRecord) -> bool {
+        let is_best = self
+            .sprint_best
+            .as_ref()
+            .is_none_or(|best| record.time < best.time);
+        if is_best {
+            self.sprint_best = Some(record);
+        }
+        is_best
+    }
+
+    fn serialise(&self) -> String {
+        let mut contents = String::new();
+        for mode in GameMode::ALL {
+            for entry in self.entries(mode) {
+                contents.push_str(&format!(
+                    "{}\t{}\t{}\t{}\t{}\t{}\n",
+                    mode.key(),
+                    entry.score,
+                    entry.lines,
+                    entry.level,
+                    entry.date,
+                    entry.initials
+                ));
+            }
+        }
+        if let Some(record) = &self.sprint_best {
+            let splits: Vec<String> = record
+                .splits
+                .iter()
+                .map(|split| split.as_millis().to_string())
+                .collect();
+            contents.push_str(&format!(
+                "{SPRINT_BEST_KEY}\t{}\t{}\t{}\n",
+                record.time.as_millis(),
+                splits.join(","),
+                record.date
+            ));
+        }
+        contents
+    }
+}
+
+// Lines that can't be read are skipped rather than throwing away the whole
+// table.
+fn parse(contents: &str) -> HighScores {
+    let mut high_scores = HighScores {
+        path: None,
+        entries: HashMap::new(),
+        sprint_best: None,
+    };
+
+    for line in contents.lines() {
+        let fields: Vec<&str> = line.split('\t').collect();
+        if let [SPRINT_BEST_KEY, time, splits, date] = fields[..] {
+            if let Some(record) = parse_sprint_record(time, splits, date) {
+                high_scores.record_sprint(record);
+            }
+            continue;
+        }
+
+        let [mode, score, lines, level, date, initials] = fields[..] else {
+            continue;
+        };
+        let (Ok(mode), Ok(score), Ok(lines), Ok(level)) = (
+            GameMode::try_from(mode),
+            score.parse(),
+            lines.parse(),
+            level.parse(),
+        ) else {
+            continue;
+        };
+
+        high_scores.insert(
+            mode,
+            HighScoreEntry {
+                score,
+                lines,
+                level,
+                date: date.to_string(),
+                initials: initials.to_string(),
+            },
+        );
+    }
+
+    high_scores
+}
+
+fn parse_sprint_record(time: &str, splits: &str, date: &str) -> Option<SprintRecord> {
+    let millis = |value: &str| value.parse().ok().map(Duration::from_millis);
+    let splits = match splits.is_empty() {
+        true => Vec::new(),
+        false => splits.split(',').map(millis).collect::<Option<_>>()?,
+    };
+
+    Some(SprintRecord {
+        time: millis(time)?,
+        splits,
+        date: date.to_string(),
+    })
+}
+
+// `$XDG_DATA_HOME/anatris/high_scores`, falling back to
+// `~/.local/share/anatris/high_scores` when it isn't set.
+pub(crate) fn default_path() -> Option<PathBuf> {
+    let data_home = env::var_os("XDG_DATA_HOME")
+        .filter(|value| !value.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| {
+            env::var_os("HOME")
+                .filter(|value| !value.is_empty())
+                .map(|home| PathBuf::from(home).join(".local").join("share"))
+        })?;
+
+    Some(data_home.join(DATA_DIRECTORY).join(FILE_NAME))
+}
+
+// Only letters and digits are kept so initials can't break the file format.
+pub(crate) fn sanitise_initials(initials: &str) -> String {
+    let initials: String = initials
+        .chars()
+        .filter(char::is_ascii_alphanumeric)
+        .take(MAX_INITIALS)
+        .collect::<String>()
+        .to_ascii_uppercase();
+
+    match initials.is_empty() {
+        true => "---".to_string(),
+        false => initials,
+    }
+}
+
+// Today's date (UTC) as YYYY-MM-DD.
+pub(crate) fn today() -> String {
+    let seconds = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs());
+    let (year, month, day) = date_from_days(seconds / SECONDS_PER_DAY);
+    format!("{year:04}-{month:02}-{day:02}")
+}
+
+// Converts days since 1970-01-01 to a calendar date, following Howard
+// Hinnant's `civil_from_days`.
+fn date_from_days(days: u64) -> (u64, u64, u64) {
+    let days = days + 719_468;
+    let era = days / 146_097;
+    let day_of_era = days % 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = match month_index < 10 {
+        true => month_index + 3,
+        false => month_index - 9,
+    };
+    let year = year_of_era + era * 400 + u64::from(month <= 2);
+
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn entry(score: u32, initials: &str) -> HighScoreEntry {
+        HighScoreEntry {
+            score,
+            lines: 10,
+            level: 1,
+            date: "2024-01-01".to_string(),
+            initials: initials.to_string(),
+        }
+    }
+
+    fn scores(high_scores: &HighScores, mode: GameMode) -> Vec<u32> {
+        high_scores
+            .entries(mode)
+            .iter()
+            .map(|entry| entry.score)
+            .collect()
+    }
+
+    #[test]
+    fn entries_are_kept_in_descending_order() {
+        let mut high_scores = HighScores::load(None);
+        for score in [300, 100, 500, 200] {
+            high_scores.insert(GameMode::AType, entry(score, "AAA"));
+        }
+
+        assert_eq!(
+            scores(&high_scores, GameMode::AType),
+            vec![500, 300, 200, 100]
+        );
+        assert_eq!(high_scores.top_score(GameMode::AType), 500);
+    }
+
+    #[test]
+    fn only_the_best_ten_are_kept() {
+        let mut high_scores = HighScores::load(None);
+        for score in 1..=12 {
+            high_scores.insert(GameMode::AType, entry(score * 100, "AAA"));
+        }
+
+        let kept = scores(&high_scores, GameMode::AType);
+        assert_eq!(kept.len(), MAX_ENTRIES);
+        assert_eq!(kept.last(), Some(&300));
+    }
+
+    #[test]
+    fn equal_scores_keep_the_earlier_entry_first() {
+        let mut high_scores = HighScores::load(None);
+        high_scores.insert(GameMode::AType, entry(100, "OLD"));
+        high_scores.insert(GameMode::AType, entry(100, "NEW"));
+
+        assert_eq!(high_scores.entries(GameMode::AType)[0].initials, "OLD");
+    }
+
+    #[test]
+    fn qualifying_needs_a_score_that_beats_a_full_table() {
+        let mut high_scores = HighScores::load(None);
+        assert!(!high_scores.qualifies(GameMode::AType, 0));
+        assert!(high_scores.qualifies(GameMode::AType, 1));
+
+        for score in 1..=10 {
+            high_scores.insert(GameMode::AType, entry(score * 100, "AAA"));
+        }
+
+        assert!(!high_scores.qualifies(GameMode::AType, 100));
+        assert!(high_scores.qualifies(GameMode::AType, 101));
+    }
+
+    #[test]
+    fn the_table_round_trips_through_text() {
+        let mut high_scores = HighScores::load(None);
+        high_scores.insert(GameMode::AType, entry(1200, "ABC"));
+        high_scores.insert(GameMode::AType, entry(800, "XYZ"));
+
+        let entries = parse(&high_scores.serialise()).entries;
+
+        assert_eq!(
+            entries.get(&GameMode::AType),
+            Some(&high_scores.entries[&GameMode::AType])
+        );
+    }
+
+    #[test]
+    fn malformed_lines_are_skipped() {
+        let entries = parse(
+            "a-type\t100\t1\t0\t2024-01-01\tAAA\n\
+             a-type\tlots\t1\t0\t2024-01-01\tBBB\n\
+             unknown\t100\t1\t0\t2024-01-01\tCCC\n\
+             not a high score\n",
+        )
+        .entries;
+
+        assert_eq!(entries[&GameMode::AType].len(), 1);
+        assert_eq!(entries[&GameMode::AType][0].initials, "AAA");
+    }
+
+    #[test]
+    fn scores_are_saved_and_loaded_from_disk() {
+        let path = env::temp_dir()
+            .join(format!("anatris-test-{}", std::process::id()))
+            .join(FILE_NAME);
+
+        let mut high_scores = HighScores::load(Some(path.clone()));
+        high_scores.insert(GameMode::AType, entry(4000, "ANA"));
+        high_scores.save().unwrap();
+
+        let loaded = HighScores::load(Some(path.clone()));
+        let _ = fs::remove_dir_all(path.parent().unwrap());
+
+        assert_eq!(
+            loaded.entries(GameMode::AType),
+            high_scores.entries(GameMode::AType)
+        );
+    }
+
+    fn sprint(seconds: u64) -> SprintRecord {
+        SprintRecord {
+            time: Duration::from_secs(seconds),
+            splits: vec![Duration::from_secs(seconds / 4); 4],
+            date: "2024-01-01".to_string(),
+        }
+    }
+
+    #[test]
+    fn only_faster_sprints_become_the_best() {
+        let mut high_scores = HighScores::load(None);
+
+        assert!(high_scores.record_sprint(sprint(90)));
+        assert!(!high_scores.record_sprint(sprint(95)));
+        assert!(!high_scores.record_sprint(sprint(90)));
+        assert!(high_scores.record_sprint(sprint(80)));
+
+        assert_eq!(high_scores.sprint_best(), Some(&sprint(80)));
+    }
+
+    #[test]
+    fn sprint_best_round_trips_through_text() {
+        let mut high_scores = HighScores::load(None);
+        high_scores.insert(GameMode::AType, entry(1200, "ABC"));
+        high_scores.record_sprint(SprintRecord {
+            time: Duration::from_millis(61_234),
+            splits: vec![Duration::from_millis(15_001), Duration::from_millis(30_002)],
+            date: "2024-01-01".to_string(),
+        });
+
+        let parsed = parse(&high_scores.serialise());
+
+        assert_eq!(parsed.sprint_best(), high_scores.sprint_best());
+        assert_eq!(parsed.entries(GameMode::AType).len(), 1);
+    }
+
+    #[test]
+    fn initials_are_sanitised() {
+        assert_eq!(sanitise_initials("ab"), "AB");
+        assert_eq!(sanitise_initials("a\tbcd"), "ABC");
+        assert_eq!(sanitise_initials(""), "---");
+    }
+
+    #[test]
+    fn days_are_converted_to_dates() {
+        assert_eq!(date_from_days(0), (1970, 1, 1));
+        assert_eq!(date_from_days(11_016), (2000, 2, 29));
+        assert_eq!(date_from_days(19_723), (2024, 1, 1));
+    }
+}
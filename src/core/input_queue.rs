@@ -0,0 +1,181 @@
+use std::collections::VecDeque;
+
+use super::game_loop::{GameAction, GameLoop};
+
+pub(crate) const INPUT_QUEUE_CAPACITY: usize = 8;
+
+// Inputs that arrived between two ticks, kept in the order they were pressed
+// so a quick rotate then move doesn't lose the move. Once the queue is full
+// any further input is dropped until the next tick drains it.
+pub(crate) struct InputQueue {
+    inputs: VecDeque<GameAction>,
+}
+
+impl InputQueue {
+    pub(crate) fn new() -> Self {
+        Self {
+            inputs: VecDeque::with_capacity(INPUT_QUEUE_CAPACITY),
+        }
+    }
+
+    pub(crate) fn push(&mut self, action: GameAction) -> bool {
+        if self.inputs.len() >= INPUT_QUEUE_CAPACITY {
+            return false;
+        }
+        self.inputs.push_back(action);
+        true
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.inputs.clear();
+    }
+
+    // Every input is handed to the game loop in order and the state machine
+    // is stepped after each one, so the next input sees where the last one
+    // left the piece. Once an input locks the piece the rest stay queued for
+    // the next piece instead of being thrown away.
+    pub(crate) fn drain<F>(&mut self, game_loop: &mut GameLoop, mut step: F)
+    where
+        F: FnMut(&mut GameLoop),
+    {
+        while game_loop.accepts_input() {
+            let Some(action) = self.inputs.pop_front() else {
+                break;
+            };
+            game_loop.handle_input(action);
+            step(game_loop);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{InputQueue, INPUT_QUEUE_CAPACITY};
+    use crate::core::{
+        game_loop::{GameAction, GameLoop, MoveActionType},
+        tetronimo::RotationDirection,
+    };
+
+    fn step(game_loop: &mut GameLoop) {
+        game_loop.do_state_machine(|_| (), |_| (), |_| (), |_| ());
+    }
+
+    fn piece_cells(game_loop: &GameLoop) -> Vec<(u16, u16)> {
+        let mut cells = vec![];
+        game_loop.draw_piece(|_, pos| cells.push((pos.x, pos.y)));
+        cells
+    }
+
+    fn started_game() -> GameLoop {
+        let (tx, _rx) = smol::channel::unbounded();
+        let mut game_loop = GameLoop::new(10, 20, tx).with_seed(Some(1));
+        step(&mut game_loop);
+        game_loop
+    }
+
+    // Plays the same inputs one tick at a time, the way they would arrive if
+    // nobody was pressing keys faster than the game ticks.
+    fn play_one_per_tick(actions: &[GameAction]) -> Vec<(u16, u16)> {
+        let mut game_loop = started_game();
+        for action in actions {
+            game_loop.handle_input(*action);
+            step(&mut game_loop);
+        }
+        piece_cells(&game_loop)
+    }
+
+    #[test]
+    fn a_burst_of_inputs_all_reach_the_game_loop() {
+        let actions = [
+            GameAction::Move(MoveActionType::Rotate(RotationDirection::Clockwise)),
+            GameAction::Move(MoveActionType::MoveLeft),
+            GameAction::Move(MoveActionType::MoveLeft),
+            GameAction::Move(MoveActionType::SoftDrop),
+        ];
+        let mut game_loop = started_game();
+        let start = piece_cells(&game_loop);
+        let mut under_test = InputQueue::new();
+        for action in actions {
+            assert!(under_test.push(action));
+        }
+
+        let mut steps = 0;
+        under_test.drain(&mut game_loop, |game_loop| {
+            steps += 1;
+            step(game_loop)
+        });
+
+        assert_eq!(actions.len(), steps);
+        assert_ne!(start, piece_cells(&game_loop));
+        assert_eq!(play_one_per_tick(&actions), piece_cells(&game_loop));
+    }
+
+    #[test]
+    fn inputs_are_drained_in_order() {
+        let mut game_loop = started_game();
+        let mut under_test = InputQueue::new();
+        under_test.push(GameAction::Move(MoveActionType::MoveLeft));
+        under_test.push(GameAction::Move(MoveActionType::Drop));
+        under_test.push(GameAction::Move(MoveActionType::MoveRight));
+
+        let mut seen = vec![];
+        under_test.drain(&mut game_loop, |game_loop| {
+            seen.push(piece_cells(game_loop));
+            step(game_loop)
+        });
+
+        let left = play_one_per_tick(&[GameAction::Move(MoveActionType::MoveLeft)]);
+        assert_eq!(2, seen.len());
+        // The first input has been handed over but not stepped yet, so the
+        // piece is still where it spawned.
+        assert_ne!(left, seen[0]);
+        assert_eq!(left, seen[1]);
+    }
+
+    #[test]
+    fn inputs_after_a_drop_wait_for_the_next_piece() {
+        let mut game_loop = started_game();
+        let mut under_test = InputQueue::new();
+        under_test.push(GameAction::Move(MoveActionType::Drop));
+        under_test.push(GameAction::Move(MoveActionType::MoveLeft));
+
+        let mut steps = 0;
+        under_test.drain(&mut game_loop, |game_loop| {
+            steps += 1;
+            step(game_loop)
+        });
+        assert_eq!(1, steps);
+
+        while !game_loop.accepts_input() {
+            step(&mut game_loop);
+        }
+        let spawned = piece_cells(&game_loop);
+        under_test.drain(&mut game_loop, step);
+
+        let moved_left: Vec<_> = spawned.iter().map(|&(x, y)| (x - 1, y)).collect();
+        assert_eq!(moved_left, piece_cells(&game_loop));
+    }
+
+    #[test]
+    fn a_full_queue_drops_new_inputs() {
+        let mut under_test = InputQueue::new();
+        for _ in 0..INPUT_QUEUE_CAPACITY {
+            assert!(under_test.push(GameAction::Move(MoveActionType::MoveLeft)));
+        }
+
+        assert!(!under_test.push(GameAction::Move(MoveActionType::MoveRight)));
+    }
+
+    #[test]
+    fn draining_empties_the_queue() {
+        let mut game_loop = started_game();
+        let mut under_test = InputQueue::new();
+        under_test.push(GameAction::Hold);
+
+        under_test.drain(&mut game_loop, step);
+        let mut steps = 0;
+        under_test.drain(&mut game_loop, |_| steps += 1);
+
+        assert_eq!(0, steps);
+    }
+}
@@ -0,0 +1,508 @@
+use std::{fs, io, path::PathBuf};
+
+use anathema::component::KeyCode;
+
+use super::settings::config_path;
+
+const FILE_NAME: &str = "keys.toml";
+
+// The keys that can be bound, written in the config file as a single
+// character or one of the names below.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Key {
+    Char(char),
+    Left,
+    Right,
+    Up,
+    Down,
+    Enter,
+    Esc,
+    Tab,
+    Backspace,
+}
+
+impl Key {
+    pub(crate) fn from_code(code: &KeyCode) -> Option<Key> {
+        match code {
+            KeyCode::Char(character) => Some(Key::Char(*character)),
+            KeyCode::Left => Some(Key::Left),
+            KeyCode::Right => Some(Key::Right),
+            KeyCode::Up => Some(Key::Up),
+            KeyCode::Down => Some(Key::Down),
+            KeyCode::Enter => Some(Key::Enter),
+            KeyCode::Esc => Some(Key::Esc),
+            KeyCode::Tab => Some(Key::Tab),
+            KeyCode::Backspace => Some(Key::Backspace),
+            _ => None,
+        }
+    }
+
+    fn name(&self) -> String {
+        match self {
+            Key::Char(' ') => "space".into(),
+            Key::Char(character) => character.to_string(),
+            Key::Left => "left".into(),
+            Key::Right => "right".into(),
+            Key::Up => "up".into(),
+            Key::Down => "down".into(),
+            Key::Enter => "enter".into(),
+            Key::Esc => "esc".into(),
+            Key::Tab => "tab".into(),
+            Key::Backspace => "backspace".into(),
+        }
+    }
+}
+
+impl TryFrom<&str> for Key {
+    type Error = String;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        let mut characters = value.chars();
+        if let (Some(character), None) = (characters.next(), characters.next()) {
+            return Ok(Key::Char(character));
+        }
+
+        match value.to_ascii_lowercase().as_str() {
+            "space" => Ok(Key::Char(' ')),
+            "left" => Ok(Key::Left),
+            "right" => Ok(Key::Right),
+            "up" => Ok(Key::Up),
+            "down" => Ok(Key::Down),
+            "enter" => Ok(Key::Enter),
+            "esc" | "escape" => Ok(Key::Esc),
+            "tab" => Ok(Key::Tab),
+            "backspace" => Ok(Key::Backspace),
+            _ => Err(format!(
+                "unknown key '{value}', expected a single character, space, left, right, up, down, enter, esc, tab or backspace"
+            )),
+        }
+    }
+}
+
+// Something to bind keys to, each section of the config file has its own.
+trait Action: Copy + PartialEq + 'static {
+    const ALL: &'static [Self];
+
+    fn name(&self) -> &'static str;
+
+    fn default_keys(&self) -> Vec<Key>;
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum PlayAction {
+    MoveLeft,
+    MoveRight,
+    SoftDrop,
+    HardDrop,
+    RotateClockwise,
+    RotateCounterClockwise,
+    Hold,
+    Pause,
+}
+
+impl Action for PlayAction {
+    const ALL: &'static [Self] = &[
+        PlayAction::MoveLeft,
+        PlayAction::MoveRight,
+        PlayAction::SoftDrop,
+        PlayAction::HardDrop,
+        PlayAction::RotateClockwise,
+        PlayAction::RotateCounterClockwise,
+        PlayAction::Hold,
+        PlayAction::Pause,
+    ];
+
+    fn name(&self) -> &'static str {
+        match self {
+            PlayAction::MoveLeft => "move_left",
+            PlayAction::MoveRight => "move_right",
+            PlayAction::SoftDrop => "soft_drop",
+            PlayAction::HardDrop => "hard_drop",
+            PlayAction::RotateClockwise => "rotate_clockwise",
+            PlayAction::RotateCounterClockwise => "rotate_counter_clockwise",
+            PlayAction::Hold => "hold",
+            PlayAction::Pause => "pause",
+        }
+    }
+
+    fn default_keys(&self) -> Vec<Key> {
+        match self {
+            PlayAction::MoveLeft => vec![Key::Char('a'), Key::Left],
+            PlayAction::MoveRight => vec![Key::Char('d'), Key::Right],
+            PlayAction::SoftDrop => vec![Key::Char('s'), Key::Down],
+            PlayAction::HardDrop => vec![Key::Char('w'), Key::Up],
+            PlayAction::RotateClockwise => vec![Key::Char(' ')],
+            PlayAction::RotateCounterClockwise => vec![Key::Char('q')],
+            PlayAction::Hold => vec![Key::Char('c')],
+            PlayAction::Pause => vec![Key::Esc],
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum MenuAction {
+    Up,
+    Down,
+    Left,
+    Right,
+    Select,
+}
+
+impl Action for MenuAction {
+    const ALL: &'static [Self] = &[
+        MenuAction::Up,
+        MenuAction::Down,
+        MenuAction::Left,
+        MenuAction::Right,
+        MenuAction::Select,
+    ];
+
+    fn name(&self) -> &'static str {
+        match self {
+            MenuAction::Up => "up",
+            MenuAction::Down => "down",
+            MenuAction::Left => "left",
+            MenuAction::Right => "right",
+            MenuAction::Select => "select",
+        }
+    }
+
+    fn default_keys(&self) -> Vec<Key> {
+        match self {
+            MenuAction::Up => vec![Key::Char('w'), Key::Up],
+            MenuAction::Down => vec![Key::Char('s'), Key::Down],
+            MenuAction::Left => vec![Key::Char('a'), Key::Left],
+            MenuAction::Right => vec![Key::Char('d'), Key::Right],
+            MenuAction::Select => vec![Key::Enter],
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+struct Bindings<A: Action> {
+    bindings: Vec<(A, Vec<Key>)>,
+}
+
+impl<A: Action> Default for Bindings<A> {
+    fn default() -> Self {
+        Self {
+            bindings: A::ALL
+                .iter()
+                .map(|action| (*action, action.default_keys()))
+                .collect(),
+        }
+    }
+}
+
+impl<A: Action> Bindings<A> {
+    fn action(&self, key: Key) -> Option<A> {
+        self.bindings
+            .iter()
+            .find(|(_, keys)| keys.contains(&key))
+            .map(|(action, _)| *action)
+    }
+
+    // Keys from the config file replace the defaults for that action.
+    fn bind(&mut self, name: &str, keys: Vec<Key>, section: &str) -> Result<(), String> {
+        let (action, bound) = self
+            .bindings
+            .iter_mut()
+            .find(|(action, _)| action.name() == name)
+            .ok_or_else(|| {
+                let names: Vec<_> = A::ALL.iter().map(|action| action.name()).collect();
+                format!(
+                    "unknown action '{name}' in [{section}], expected one of {}",
+                    names.join(", ")
+                )
+            })?;
+        if keys.is_empty() {
+            return Err(format!(
+                "{} in [{section}] needs at least one key",
+                action.name()
+            ));
+        }
+
+        *bound = keys;
+        Ok(())
+    }
+
+    fn check_conflicts(&self, section: &str) -> Result<(), String> {
+        for (index, (action, keys)) in self.bindings.iter().enumerate() {
+            for (other, other_keys) in &self.bindings[index + 1..] {
+                if let Some(key) = keys.iter().find(|key| other_keys.contains(key)) {
+                    return Err(format!(
+                        "key '{}' is bound to both {} and {} in [{section}]",
+                        key.name(),
+                        action.name(),
+                        other.name()
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+// Which keys do what, while playing and on the menus. Anything not in the
+// config file keeps its default binding, arrows and WASD both work.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub(crate) struct Keymap {
+    game: Bindings<PlayAction>,
+    menu: Bindings<MenuAction>,
+}
+
+impl Keymap {
+    pub(crate) fn play_action(&self, code: &KeyCode) -> Option<PlayAction> {
+        Key::from_code(code).and_then(|key| self.game.action(key))
+    }
+
+    pub(crate) fn menu_action(&self, code: &KeyCode) -> Option<MenuAction> {
+        Key::from_code(code).and_then(|key| self.menu.action(key))
+    }
+}
+
+// Reads the key bindings from `path`, or from the default location if there
+// isn't one. Only a missing default file falls back to the default bindings.
+pub(crate) fn load(path: Option<PathBuf>) -> Result<Keymap, String> {
+    let (path, required) = match path {
+        Some(path) => (path, true),
+        None => match default_path() {
+            Some(path) => (path, false),
+            None => return Ok(Keymap::default()),
+        },
+    };
+
+    match fs::read_to_string(&path) {
+        Ok(contents) => parse(&contents).map_err(|error| format!("{}: {error}", path.display())),
+        Err(error) if !required && error.kind() == io::ErrorKind::NotFound => Ok(Keymap::default()),
+        Err(error) => Err(format!(
+            "could not read key bindings from {}: {error}",
+            path.display()
+        )),
+    }
+}
+
+pub(crate) fn default_path() -> Option<PathBuf> {
+    config_path(FILE_NAME)
+}
+
+// A small subset of TOML, a [game] and a [menu] table where every entry is
+// `action = "key"` or `action = ["key", "key"]`.
+pub(crate) fn parse(contents: &str) -> Result<Keymap, String> {
+    let mut keymap = Keymap::default();
+    let mut section = None;
+
+    for (index, line) in contents.lines().enumerate() {
+        let line_number = index + 1;
+        let line = strip_comment(line).trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        if let Some(name) = line
+            .strip_prefix('[')
+            .and_then(|line| line.strip_suffix(']'))
+        {
+            section = match name.trim() {
+                "game" => Some("game"),
+                "menu" => Some("menu"),
+                other => {
+                    return Err(format!(
+                        "line {line_number}: unknown section [{other}], expected [game] or [menu]"
+                    ))
+                }
+            };
+            continue;
+        }
+
+        let (name, value) = line.split_once('=').ok_or_else(|| {
+            format!("line {line_number}: expected 'action = \"key\"', found '{line}'")
+        })?;
+        let name = name.trim();
+        let keys =
+            parse_keys(value.trim()).map_err(|error| format!("line {line_number}: {error}"))?;
+        match section {
+            Some("game") => keymap.game.bind(name, keys, "game"),
+            Some(_) => keymap.menu.bind(name, keys, "menu"),
+            None => Err(format!("'{name}' has to be in a [game] or [menu] section")),
+        }
+        .map_err(|error| format!("line {line_number}: {error}"))?;
+    }
+
+    keymap.game.check_conflicts("game")?;
+    keymap.menu.check_conflicts("menu")?;
+    Ok(keymap)
+}
+
+fn parse_keys(value: &str) -> Result<Vec<Key>, String> {
+    let list = match value.strip_prefix('[') {
+        Some(list) => list
+            .strip_suffix(']')
+            .ok_or_else(|| format!("missing ']' at the end of '{value}'"))?,
+        None => value,
+    };
+
+    let mut keys = vec![];
+    let mut rest = list.trim();
+    while !rest.is_empty() {
+        let quoted = rest
+            .strip_prefix('"')
+            .ok_or_else(|| format!("expected a quoted key name, found '{rest}'"))?;
+        let end = quoted
+            .find('"')
+            .ok_or_else(|| format!("missing '\"' at the end of '{rest}'"))?;
+        keys.push(Key::try_from(&quoted[..end])?);
+
+        rest = quoted[end + 1..].trim_start();
+        match rest.strip_prefix(',') {
+            Some(next) => rest = next.trim_start(),
+            None if rest.is_empty() => (),
+            None => return Err(format!("expected ',' between keys, found '{rest}'")),
+        }
+    }
+    Ok(keys)
+}
+
+// Everything after a '#' is a comment, unless the '#' is a quoted key.
+fn strip_comment(line: &str) -> &str {
+    let mut quoted = false;
+    for (index, character) in line.char_indices() {
+        match character {
+            '"' => quoted = !quoted,
+            '#' if !quoted => return &line[..index],
+            _ => (),
+        }
+    }
+    line
+}
+
+#[cfg(test)]
+mod test {
+    use anathema::component::KeyCode;
+
+    use super::{parse, Key, Keymap, MenuAction, PlayAction};
+
+    #[test]
+    fn arrows_and_wasd_work_by_default() {
+        let under_test = Keymap::default();
+
+        assert_eq!(
+            Some(PlayAction::MoveLeft),
+            under_test.play_action(&KeyCode::Left)
+        );
+        assert_eq!(
+            Some(PlayAction::MoveLeft),
+            under_test.play_action(&KeyCode::Char('a'))
+        );
+        assert_eq!(
+            Some(PlayAction::HardDrop),
+            under_test.play_action(&KeyCode::Up)
+        );
+        assert_eq!(
+            Some(MenuAction::Down),
+            under_test.menu_action(&KeyCode::Down)
+        );
+        assert_eq!(
+            Some(MenuAction::Down),
+            under_test.menu_action(&KeyCode::Char('s'))
+        );
+        assert_eq!(None, under_test.play_action(&KeyCode::Char('z')));
+    }
+
+    #[test]
+    fn empty_file_keeps_the_defaults() {
+        assert_eq!(Ok(Keymap::default()), parse("# nothing here\n\n"));
+    }
+
+    #[test]
+    fn bindings_replace_the_defaults_for_that_action() {
+        let contents = r##"
+            [game]
+            rotate_clockwise = ["x", "space"] # both work
+            hold = "#"
+
+            [menu]
+            select = ["enter", "space"]
+        "##;
+
+        let under_test = parse(contents).unwrap();
+
+        assert_eq!(
+            Some(PlayAction::RotateClockwise),
+            under_test.play_action(&KeyCode::Char('x'))
+        );
+        assert_eq!(
+            Some(PlayAction::Hold),
+            under_test.play_action(&KeyCode::Char('#'))
+        );
+        assert_eq!(None, under_test.play_action(&KeyCode::Char('c')));
+        assert_eq!(
+            Some(MenuAction::Select),
+            under_test.menu_action(&KeyCode::Char(' '))
+        );
+    }
+
+    #[test]
+    fn the_same_key_can_be_used_in_different_sections() {
+        let contents = "[game]\npause = \"p\"\n[menu]\nselect = \"p\"";
+
+        let under_test = parse(contents).unwrap();
+
+        assert_eq!(
+            Some(PlayAction::Pause),
+            under_test.play_action(&KeyCode::Char('p'))
+        );
+        assert_eq!(
+            Some(MenuAction::Select),
+            under_test.menu_action(&KeyCode::Char('p'))
+        );
+    }
+
+    #[test]
+    fn conflicting_bindings_are_rejected() {
+        let contents = "[game]\nhold = \"a\"";
+
+        assert_eq!(
+            Err("key 'a' is bound to both move_left and hold in [game]".to_string()),
+            parse(contents)
+        );
+    }
+
+    #[test]
+    fn unknown_actions_are_rejected() {
+        let error = parse("[game]\njump = \"j\"").unwrap_err();
+
+        assert!(error.starts_with("line 2: unknown action 'jump' in [game]"));
+    }
+
+    #[test]
+    fn unknown_keys_are_rejected() {
+        let error = parse("[menu]\nup = \"page-up\"").unwrap_err();
+
+        assert!(error.starts_with("line 2: unknown key 'page-up'"));
+    }
+
+    #[test]
+    fn malformed_lines_are_rejected() {
+        assert!(parse("hold = \"c\"")
+            .unwrap_err()
+            .contains("[game] or [menu]"));
+        assert!(parse("[keys]")
+            .unwrap_err()
+            .contains("unknown section [keys]"));
+        assert!(parse("[game]\nhold").unwrap_err().starts_with("line 2"));
+        assert!(parse("[game]\nhold = c").is_err());
+        assert!(parse("[game]\nhold = [\"c\"").is_err());
+        assert!(parse("[game]\nhold = []")
+            .unwrap_err()
+            .contains("at least one key"));
+    }
+
+    #[test]
+    fn key_names_parse() {
+        assert_eq!(Ok(Key::Char(' ')), Key::try_from("space"));
+        assert_eq!(Ok(Key::Esc), Key::try_from("Escape"));
+        assert_eq!(Ok(Key::Char('Q')), Key::try_from("Q"));
+        assert!(Key::try_from("").is_err());
+    }
+}
@@ -0,0 +1,79 @@
+// A list of menu rows with one of them selected. Rows can be hidden, for
+// example settings that only apply to some game modes, and the selection
+// skips over them.
+pub(crate) struct Menu<T: 'static> {
+    items: &'static [T],
+    selected: usize,
+}
+
+impl<T: Copy + PartialEq> Menu<T> {
+    pub(crate) fn new(items: &'static [T]) -> Self {
+        Self { items, selected: 0 }
+    }
+
+    pub(crate) fn selected(&self) -> T {
+        self.items[self.selected]
+    }
+
+    // Position of the selected row, hidden rows included.
+    pub(crate) fn index(&self) -> usize {
+        self.selected
+    }
+
+    pub(crate) fn reset(&mut self) {
+        self.selected = 0;
+    }
+
+    // Moves the selection, wrapping around at either end.
+    pub(crate) fn move_selection<F>(&mut self, change: isize, shown: F)
+    where
+        F: Fn(T) -> bool,
+    {
+        let count = self.items.len() as isize;
+        let mut selected = self.selected as isize;
+        for _ in 0..count {
+            selected = (selected + change).rem_euclid(count);
+            if shown(self.items[selected as usize]) {
+                self.selected = selected as usize;
+                return;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Menu;
+
+    const ITEMS: [char; 4] = ['a', 'b', 'c', 'd'];
+
+    #[test]
+    fn selection_wraps_around() {
+        let mut under_test = Menu::new(&ITEMS);
+
+        under_test.move_selection(-1, |_| true);
+        assert_eq!('d', under_test.selected());
+
+        under_test.move_selection(1, |_| true);
+        assert_eq!('a', under_test.selected());
+    }
+
+    #[test]
+    fn hidden_rows_are_skipped() {
+        let mut under_test = Menu::new(&ITEMS);
+
+        under_test.move_selection(1, |item| item != 'b');
+
+        assert_eq!('c', under_test.selected());
+        assert_eq!(2, under_test.index());
+    }
+
+    #[test]
+    fn nothing_changes_when_every_other_row_is_hidden() {
+        let mut under_test = Menu::new(&ITEMS);
+
+        under_test.move_selection(1, |item| item == 'a');
+
+        assert_eq!('a', under_test.selected());
+    }
+}
@@ -1,3 +1,14 @@
+pub(crate) mod auto_shift;
 pub(crate) mod game_loop;
+pub(crate) mod game_mode;
 pub(crate) mod global_state;
+pub(crate) mod gravity;
+pub(crate) mod held_key;
+pub(crate) mod high_scores;
+pub(crate) mod input_queue;
+pub(crate) mod keymap;
+pub(crate) mod menu;
+pub(crate) mod piece_generator;
+pub(crate) mod scoring;
+pub(crate) mod settings;
 pub(crate) mod tetronimo;
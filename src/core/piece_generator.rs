@@ -0,0 +1,184 @@
+use rand::{seq::SliceRandom, Rng, RngCore};
+
+use super::tetronimo::TetronimoShape;
+
+const ALL_SHAPES: [TetronimoShape; 7] = [
+    TetronimoShape::IShape,
+    TetronimoShape::JShape,
+    TetronimoShape::LShape,
+    TetronimoShape::OShape,
+    TetronimoShape::SShape,
+    TetronimoShape::TShape,
+    TetronimoShape::ZShape,
+];
+
+// Decides the order in which pieces are handed out to the player.
+pub(crate) trait PieceGenerator {
+    fn next_piece(&mut self, rng: &mut dyn RngCore) -> TetronimoShape;
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub(crate) enum PieceGeneratorType {
+    #[default]
+    SevenBag,
+    Nes,
+    Uniform,
+}
+
+impl PieceGeneratorType {
+    pub(crate) fn create(&self) -> Box<dyn PieceGenerator> {
+        match self {
+            PieceGeneratorType::SevenBag => Box::new(SevenBagGenerator::default()),
+            PieceGeneratorType::Nes => Box::new(NesGenerator::default()),
+            PieceGeneratorType::Uniform => Box::new(UniformGenerator),
+        }
+    }
+}
+
+impl TryFrom<&str> for PieceGeneratorType {
+    type Error = String;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value {
+            "bag" => Ok(PieceGeneratorType::SevenBag),
+            "nes" => Ok(PieceGeneratorType::Nes),
+            "uniform" => Ok(PieceGeneratorType::Uniform),
+            _ => Err(format!(
+                "unknown randomizer '{value}', expected one of: bag, nes, uniform"
+            )),
+        }
+    }
+}
+
+// All seven shapes are shuffled into a bag and dealt out one at a time, once
+// the bag is empty a new one is shuffled. This guarantees there is never
+// more than twelve pieces between two of the same shape.
+#[derive(Default)]
+pub(crate) struct SevenBagGenerator {
+    bag: Vec<TetronimoShape>,
+}
+
+impl PieceGenerator for SevenBagGenerator {
+    fn next_piece(&mut self, rng: &mut dyn RngCore) -> TetronimoShape {
+        if self.bag.is_empty() {
+            self.bag = ALL_SHAPES.to_vec();
+            self.bag.shuffle(rng);
+        }
+        self.bag.pop().expect("bag has just been refilled")
+    }
+}
+
+// The NES picks one of eight values, if that is the dummy eighth value or the
+// same shape as last time it rerolls once between the seven shapes and takes
+// whatever comes out.
+#[derive(Default)]
+pub(crate) struct NesGenerator {
+    previous: Option<TetronimoShape>,
+}
+
+impl PieceGenerator for NesGenerator {
+    fn next_piece(&mut self, rng: &mut dyn RngCore) -> TetronimoShape {
+        let roll = rng.random_range(0..=ALL_SHAPES.len());
+        let shape = match ALL_SHAPES.get(roll) {
+            Some(shape) if Some(shape) != self.previous.as_ref() => shape.clone(),
+            _ => rng.random(),
+        };
+        self.previous = Some(shape.clone());
+        shape
+    }
+}
+
+pub(crate) struct UniformGenerator;
+
+impl PieceGenerator for UniformGenerator {
+    fn next_piece(&mut self, rng: &mut dyn RngCore) -> TetronimoShape {
+        rng.random()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use super::{PieceGeneratorType, ALL_SHAPES};
+    use crate::core::tetronimo::TetronimoShape;
+
+    const SAMPLES: usize = 7000;
+
+    fn generate(generator_type: PieceGeneratorType, seed: u64) -> Vec<TetronimoShape> {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut generator = generator_type.create();
+        (0..SAMPLES).map(|_| generator.next_piece(&mut rng)).collect()
+    }
+
+    fn count(pieces: &[TetronimoShape], shape: &TetronimoShape) -> usize {
+        pieces.iter().filter(|piece| *piece == shape).count()
+    }
+
+    #[test]
+    fn seven_bag_contains_every_shape() {
+        for seed in 0..20 {
+            let pieces = generate(PieceGeneratorType::SevenBag, seed);
+            for bag in pieces.chunks(7) {
+                for shape in ALL_SHAPES {
+                    assert_eq!(1, count(bag, &shape), "{shape:?} in {bag:?}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn seven_bag_drought_is_bounded() {
+        let pieces = generate(PieceGeneratorType::SevenBag, 42);
+        for shape in ALL_SHAPES {
+            let positions: Vec<usize> = pieces
+                .iter()
+                .enumerate()
+                .filter(|(_, piece)| **piece == shape)
+                .map(|(position, _)| position)
+                .collect();
+            for gap in positions.windows(2) {
+                assert!(gap[1] - gap[0] <= 13, "{shape:?} drought of {}", gap[1] - gap[0]);
+            }
+        }
+    }
+
+    #[test]
+    fn uniform_produces_every_shape() {
+        let pieces = generate(PieceGeneratorType::Uniform, 42);
+        let expected = SAMPLES / ALL_SHAPES.len();
+        for shape in ALL_SHAPES {
+            let occurrences = count(&pieces, &shape);
+            assert!(
+                occurrences > expected * 8 / 10 && occurrences < expected * 12 / 10,
+                "{shape:?} appeared {occurrences} times"
+            );
+        }
+    }
+
+    #[test]
+    fn nes_produces_every_shape() {
+        let pieces = generate(PieceGeneratorType::Nes, 42);
+        for shape in ALL_SHAPES {
+            assert!(count(&pieces, &shape) > 0, "{shape:?} never appeared");
+        }
+    }
+
+    #[test]
+    fn nes_rerolls_repeats() {
+        let pieces = generate(PieceGeneratorType::Nes, 42);
+        let repeats = pieces.windows(2).filter(|pair| pair[0] == pair[1]).count();
+
+        // A repeat needs both the first roll and the reroll to land on the
+        // previous shape, roughly 1 in 28 rather than 1 in 7.
+        assert!(repeats < SAMPLES / 14, "{repeats} repeats");
+    }
+
+    #[test]
+    fn generator_type_from_str() {
+        assert_eq!(Ok(PieceGeneratorType::SevenBag), "bag".try_into());
+        assert_eq!(Ok(PieceGeneratorType::Nes), "nes".try_into());
+        assert_eq!(Ok(PieceGeneratorType::Uniform), "uniform".try_into());
+        assert!(PieceGeneratorType::try_from("random").is_err());
+    }
+}
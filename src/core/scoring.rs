@@ -0,0 +1,256 @@
+// Works out how many points the player is given for the things they do. The
+// level passed in counts from zero the same way the game displays it.
+pub(crate) trait ScoringSystem {
+    fn line_clear(&self, lines: u16, level: u16) -> u32;
+    fn soft_drop(&self, rows: u16) -> u32;
+    fn hard_drop(&self, rows: u16) -> u32;
+
+    // Systems that don't know about T-spins score them as a regular clear.
+    fn t_spin(&self, _t_spin: TSpin, lines: u16, level: u16) -> u32 {
+        self.line_clear(lines, level)
+    }
+
+    // Bonus for a lock that clears lines straight after another one, the
+    // first clear of a combo is combo 0.
+    fn combo(&self, _combo: u16, _level: u16) -> u32 {
+        0
+    }
+
+    // Points for a Tetris or T-spin that follows another one.
+    fn back_to_back(&self, points: u32) -> u32 {
+        points
+    }
+}
+
+// A T piece rotated into a spot surrounded on three corners.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum TSpin {
+    Full,
+    Mini,
+}
+
+impl TSpin {
+    // The callout shown next to the arena, e.g. T-SPIN DOUBLE.
+    pub(crate) fn label(&self, lines: u16) -> String {
+        let name = match self {
+            TSpin::Full => "T-SPIN",
+            TSpin::Mini => "MINI T-SPIN",
+        };
+        match lines {
+            0 => name.to_string(),
+            1 => format!("{name} SINGLE"),
+            2 => format!("{name} DOUBLE"),
+            _ => format!("{name} TRIPLE"),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub(crate) enum ScoringSystemType {
+    Nes,
+    #[default]
+    Guideline,
+}
+
+impl ScoringSystemType {
+    pub(crate) fn create(&self) -> Box<dyn ScoringSystem> {
+        match self {
+            ScoringSystemType::Nes => Box::new(NesScoring),
+            ScoringSystemType::Guideline => Box::new(GuidelineScoring),
+        }
+    }
+}
+
+impl TryFrom<&str> for ScoringSystemType {
+    type Error = String;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value {
+            "nes" => Ok(ScoringSystemType::Nes),
+            "guideline" => Ok(ScoringSystemType::Guideline),
+            _ => Err(format!(
+                "unknown scoring system '{value}', expected one of: nes, guideline"
+            )),
+        }
+    }
+}
+
+// Single, double, triple and tetris multiplied by the level plus one. There
+// is no hard drop on the NES and soft drop is worth a point for each row.
+const NES_LINE_CLEAR: [u32; 5] = [0, 40, 100, 300, 1200];
+
+pub(crate) struct NesScoring;
+
+impl ScoringSystem for NesScoring {
+    fn line_clear(&self, lines: u16, level: u16) -> u32 {
+        let index = (lines as usize).min(NES_LINE_CLEAR.len() - 1);
+        NES_LINE_CLEAR[index] * (level as u32 + 1)
+    }
+
+    fn soft_drop(&self, rows: u16) -> u32 {
+        rows as u32
+    }
+
+    fn hard_drop(&self, _rows: u16) -> u32 {
+        0
+    }
+}
+
+// The guideline levels count from one so the multiplier is the displayed
+// level plus one.
+const GUIDELINE_LINE_CLEAR: [u32; 5] = [0, 100, 300, 500, 800];
+const GUIDELINE_T_SPIN: [u32; 4] = [400, 800, 1200, 1600];
+const GUIDELINE_MINI_T_SPIN: [u32; 3] = [100, 200, 400];
+const GUIDELINE_SOFT_DROP_PER_ROW: u32 = 1;
+const GUIDELINE_HARD_DROP_PER_ROW: u32 = 2;
+const GUIDELINE_COMBO: u32 = 50;
+
+pub(crate) struct GuidelineScoring;
+
+impl ScoringSystem for GuidelineScoring {
+    fn line_clear(&self, lines: u16, level: u16) -> u32 {
+        let index = (lines as usize).min(GUIDELINE_LINE_CLEAR.len() - 1);
+        GUIDELINE_LINE_CLEAR[index] * (level as u32 + 1)
+    }
+
+    fn soft_drop(&self, rows: u16) -> u32 {
+        rows as u32 * GUIDELINE_SOFT_DROP_PER_ROW
+    }
+
+    fn hard_drop(&self, rows: u16) -> u32 {
+        rows as u32 * GUIDELINE_HARD_DROP_PER_ROW
+    }
+
+    fn t_spin(&self, t_spin: TSpin, lines: u16, level: u16) -> u32 {
+        let table: &[u32] = match t_spin {
+            TSpin::Full => &GUIDELINE_T_SPIN,
+            TSpin::Mini => &GUIDELINE_MINI_T_SPIN,
+        };
+        let index = (lines as usize).min(table.len() - 1);
+        table[index] * (level as u32 + 1)
+    }
+
+    fn combo(&self, combo: u16, level: u16) -> u32 {
+        GUIDELINE_COMBO * combo as u32 * (level as u32 + 1)
+    }
+
+    fn back_to_back(&self, points: u32) -> u32 {
+        points * 3 / 2
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{GuidelineScoring, NesScoring, ScoringSystem, ScoringSystemType, TSpin};
+
+    #[test]
+    fn nes_line_clear() {
+        let cases = [
+            (0, 0, 0),
+            (1, 0, 40),
+            (2, 0, 100),
+            (3, 0, 300),
+            (4, 0, 1200),
+            (1, 9, 400),
+            (2, 9, 1000),
+            (3, 9, 3000),
+            (4, 9, 12000),
+            (4, 29, 36000),
+        ];
+
+        for (lines, level, score) in cases {
+            assert_eq!(score, NesScoring.line_clear(lines, level), "{lines} at {level}");
+        }
+    }
+
+    #[test]
+    fn nes_drops() {
+        assert_eq!(0, NesScoring.soft_drop(0));
+        assert_eq!(12, NesScoring.soft_drop(12));
+        assert_eq!(0, NesScoring.hard_drop(12));
+    }
+
+    #[test]
+    fn guideline_line_clear() {
+        let cases = [
+            (0, 0, 0),
+            (1, 0, 100),
+            (2, 0, 300),
+            (3, 0, 500),
+            (4, 0, 800),
+            (1, 4, 500),
+            (2, 4, 1500),
+            (3, 4, 2500),
+            (4, 4, 4000),
+        ];
+
+        for (lines, level, score) in cases {
+            assert_eq!(score, GuidelineScoring.line_clear(lines, level), "{lines} at {level}");
+        }
+    }
+
+    #[test]
+    fn guideline_drops() {
+        assert_eq!(0, GuidelineScoring.soft_drop(0));
+        assert_eq!(7, GuidelineScoring.soft_drop(7));
+        assert_eq!(0, GuidelineScoring.hard_drop(0));
+        assert_eq!(36, GuidelineScoring.hard_drop(18));
+    }
+
+    #[test]
+    fn guideline_t_spins() {
+        let cases = [
+            (TSpin::Full, 0, 0, 400),
+            (TSpin::Full, 1, 0, 800),
+            (TSpin::Full, 2, 0, 1200),
+            (TSpin::Full, 3, 0, 1600),
+            (TSpin::Full, 2, 4, 6000),
+            (TSpin::Mini, 0, 0, 100),
+            (TSpin::Mini, 1, 0, 200),
+            (TSpin::Mini, 2, 0, 400),
+            (TSpin::Mini, 1, 4, 1000),
+        ];
+
+        for (t_spin, lines, level, score) in cases {
+            assert_eq!(
+                score,
+                GuidelineScoring.t_spin(t_spin, lines, level),
+                "{t_spin:?} {lines} at {level}"
+            );
+        }
+    }
+
+    #[test]
+    fn nes_scores_t_spins_as_line_clears() {
+        assert_eq!(0, NesScoring.t_spin(TSpin::Full, 0, 0));
+        assert_eq!(100, NesScoring.t_spin(TSpin::Full, 2, 0));
+    }
+
+    #[test]
+    fn guideline_combo_and_back_to_back() {
+        assert_eq!(0, GuidelineScoring.combo(0, 0));
+        assert_eq!(150, GuidelineScoring.combo(3, 0));
+        assert_eq!(500, GuidelineScoring.combo(2, 4));
+        assert_eq!(1200, GuidelineScoring.back_to_back(800));
+    }
+
+    #[test]
+    fn nes_has_no_combo_or_back_to_back_bonus() {
+        assert_eq!(0, NesScoring.combo(3, 9));
+        assert_eq!(1200, NesScoring.back_to_back(1200));
+    }
+
+    #[test]
+    fn t_spin_labels() {
+        assert_eq!("T-SPIN", TSpin::Full.label(0));
+        assert_eq!("T-SPIN DOUBLE", TSpin::Full.label(2));
+        assert_eq!("MINI T-SPIN SINGLE", TSpin::Mini.label(1));
+    }
+
+    #[test]
+    fn scoring_system_from_str() {
+        assert_eq!(Ok(ScoringSystemType::Nes), "nes".try_into());
+        assert_eq!(Ok(ScoringSystemType::Guideline), "guideline".try_into());
+        assert!(ScoringSystemType::try_from("tgm").is_err());
+    }
+}
@@ -0,0 +1,381 @@
+use std::{
+    env, fs, io,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use super::{
+    auto_shift::{DEFAULT_ARR, DEFAULT_DAS},
+    game_loop::{
+        DEFAULT_PREVIEW_COUNT, MAX_GARBAGE_HEIGHT, MAX_PREVIEW_COUNT, MAX_START_LEVEL,
+        MIN_PREVIEW_COUNT,
+    },
+    game_mode::{GameMode, GameSettings},
+};
+
+const CONFIG_DIRECTORY: &str = "anatris";
+const FILE_NAME: &str = "settings.toml";
+
+const MAX_DAS: Duration = Duration::from_millis(500);
+const MAX_ARR: Duration = Duration::from_millis(200);
+const DAS_STEP: Duration = Duration::from_millis(10);
+const ARR_STEP: Duration = Duration::from_millis(5);
+
+// Colours used for the menus and the panels around the arena.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub(crate) enum Theme {
+    #[default]
+    Classic,
+    Ocean,
+    Forest,
+    Mono,
+}
+
+impl Theme {
+    pub(crate) const ALL: [Theme; 4] = [Theme::Classic, Theme::Ocean, Theme::Forest, Theme::Mono];
+
+    // Name used for the theme in the settings file.
+    pub(crate) fn key(&self) -> &'static str {
+        match self {
+            Theme::Classic => "classic",
+            Theme::Ocean => "ocean",
+            Theme::Forest => "forest",
+            Theme::Mono => "mono",
+        }
+    }
+
+    pub(crate) fn label(&self) -> &'static str {
+        match self {
+            Theme::Classic => "CLASSIC",
+            Theme::Ocean => "OCEAN",
+            Theme::Forest => "FOREST",
+            Theme::Mono => "MONO",
+        }
+    }
+
+    pub(crate) fn foreground(&self) -> &'static str {
+        match self {
+            Theme::Classic => "red",
+            Theme::Ocean => "blue",
+            Theme::Forest => "green",
+            Theme::Mono => "white",
+        }
+    }
+
+    pub(crate) fn background(&self) -> &'static str {
+        match self {
+            Theme::Classic => "white",
+            Theme::Ocean => "white",
+            Theme::Forest => "black",
+            Theme::Mono => "black",
+        }
+    }
+
+    pub(crate) fn cycle(&self, change: isize) -> Theme {
+        let count = Theme::ALL.len() as isize;
+        let index = Theme::ALL
+            .iter()
+            .position(|theme| theme == self)
+            .unwrap_or_default() as isize;
+        Theme::ALL[(index + change).rem_euclid(count) as usize]
+    }
+}
+
+impl TryFrom<&str> for Theme {
+    type Error = String;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        Theme::ALL
+            .into_iter()
+            .find(|theme| theme.key() == value)
+            .ok_or_else(|| format!("unknown theme '{value}'"))
+    }
+}
+
+// A single row on the settings screen.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Setting {
+    StartLevel,
+    Mode,
+    GarbageHeight,
+    Ghost,
+    Das,
+    Arr,
+    Preview,
+    Theme,
+}
+
+// Everything that can be changed on the settings screen, saved to the user
+// config file whenever it changes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct Settings {
+    pub(crate) start_level: u16,
+    pub(crate) mode: GameMode,
+    pub(crate) garbage_height: u16,
+    pub(crate) ghost: bool,
+    pub(crate) das: Duration,
+    pub(crate) arr: Duration,
+    pub(crate) preview: usize,
+    pub(crate) theme: Theme,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            start_level: 0,
+            mode: GameMode::default(),
+            garbage_height: 0,
+            ghost: true,
+            das: DEFAULT_DAS,
+            arr: DEFAULT_ARR,
+            preview: DEFAULT_PREVIEW_COUNT,
+            theme: Theme::default(),
+        }
+    }
+}
+
+impl Settings {
+    pub(crate) fn game_settings(&self) -> GameSettings {
+        GameSettings {
+            mode: self.mode,
+            start_level: self.start_level,
+            garbage_height: self.garbage_height,
+        }
+    }
+
+    // Steps a setting up or down, numbers stop at either end of their range
+    // while the choices wrap around.
+    pub(crate) fn adjust(&mut self, setting: Setting, change: i32) {
+        match setting {
+            Setting::StartLevel => {
+                self.start_level = step(self.start_level, change, MAX_START_LEVEL);
+            }
+            Setting::Mode => self.mode = self.mode.cycle(change as isize),
+            Setting::GarbageHeight => {
+                self.garbage_height = step(self.garbage_height, change, MAX_GARBAGE_HEIGHT);
+            }
+            Setting::Ghost => self.ghost = !self.ghost,
+            Setting::Das => self.das = step_duration(self.das, change, DAS_STEP, MAX_DAS),
+            Setting::Arr => self.arr = step_duration(self.arr, change, ARR_STEP, MAX_ARR),
+            Setting::Preview => {
+                let preview = self.preview as i32 + change;
+                self.preview =
+                    preview.clamp(MIN_PREVIEW_COUNT as i32, MAX_PREVIEW_COUNT as i32) as usize;
+            }
+            Setting::Theme => self.theme = self.theme.cycle(change as isize),
+        }
+    }
+
+    // Takes a single setting from `other`, so only what was changed on the
+    // settings screen is saved and not what was given on the command line.
+    pub(crate) fn copy_setting(&mut self, setting: Setting, other: &Settings) {
+        match setting {
+            Setting::StartLevel => self.start_level = other.start_level,
+            Setting::Mode => self.mode = other.mode,
+            Setting::GarbageHeight => self.garbage_height = other.garbage_height,
+            Setting::Ghost => self.ghost = other.ghost,
+            Setting::Das => self.das = other.das,
+            Setting::Arr => self.arr = other.arr,
+            Setting::Preview => self.preview = other.preview,
+            Setting::Theme => self.theme = other.theme,
+        }
+    }
+
+    fn serialise(&self) -> String {
+        format!(
+            "start_level = {}\nmode = \"{}\"\ngarbage_height = {}\nghost = {}\ndas = {}\narr = {}\npreview = {}\ntheme = \"{}\"\n",
+            self.start_level,
+            self.mode.key(),
+            self.garbage_height,
+            self.ghost,
+            self.das.as_millis(),
+            self.arr.as_millis(),
+            self.preview,
+            self.theme.key()
+        )
+    }
+}
+
+fn step(value: u16, change: i32, max: u16) -> u16 {
+    (value as i32 + change).clamp(0, max as i32) as u16
+}
+
+fn step_duration(value: Duration, change: i32, step: Duration, max: Duration) -> Duration {
+    match change.is_negative() {
+        true => value.saturating_sub(step),
+        false => (value + step).min(max),
+    }
+}
+
+// A missing or unreadable file just means nothing has been changed yet.
+pub(crate) fn load(path: Option<&Path>) -> Settings {
+    path.and_then(|path| fs::read_to_string(path).ok())
+        .map(|contents| parse(&contents))
+        .unwrap_or_default()
+}
+
+pub(crate) fn save(path: Option<&Path>, settings: &Settings) -> io::Result<()> {
+    let Some(path) = path else {
+        return Ok(());
+    };
+
+    if let Some(directory) = path.parent() {
+        fs::create_dir_all(directory)?;
+    }
+    fs::write(path, settings.serialise())
+}
+
+pub(crate) fn default_path() -> Option<PathBuf> {
+    config_path(FILE_NAME)
+}
+
+// Where the user's config files live, `$XDG_CONFIG_HOME/anatris` or
+// `~/.config/anatris`.
+pub(crate) fn config_path(file_name: &str) -> Option<PathBuf> {
+    let config_home = env::var_os("XDG_CONFIG_HOME")
+        .filter(|value| !value.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| {
+            env::var_os("HOME")
+                .filter(|value| !value.is_empty())
+                .map(|home| PathBuf::from(home).join(".config"))
+        })?;
+
+    Some(config_home.join(CONFIG_DIRECTORY).join(file_name))
+}
+
+// The file is written by the game, so anything that can't be read keeps its
+// default rather than stopping the game from starting.
+fn parse(contents: &str) -> Settings {
+    let mut settings = Settings::default();
+
+    for line in contents.lines() {
+        let Some((key, value)) = line.split_once('=') else {
+            continue;
+        };
+        let value = value.trim().trim_matches('"');
+        let millis = |value: &str| value.parse().ok().map(Duration::from_millis);
+
+        match key.trim() {
+            "start_level" => {
+                if let Ok(level) = value.parse::<u16>() {
+                    settings.start_level = level.min(MAX_START_LEVEL);
+                }
+            }
+            "mode" => {
+                if let Ok(mode) = GameMode::try_from(value) {
+                    settings.mode = mode;
+                }
+            }
+            "garbage_height" => {
+                if let Ok(height) = value.parse::<u16>() {
+                    settings.garbage_height = height.min(MAX_GARBAGE_HEIGHT);
+                }
+            }
+            "ghost" => {
+                if let Ok(ghost) = value.parse() {
+                    settings.ghost = ghost;
+                }
+            }
+            "das" => {
+                if let Some(das) = millis(value) {
+                    settings.das = das;
+                }
+            }
+            "arr" => {
+                if let Some(arr) = millis(value) {
+                    settings.arr = arr;
+                }
+            }
+            "preview" => {
+                if let Ok(preview) = value.parse::<usize>() {
+                    settings.preview = preview.clamp(MIN_PREVIEW_COUNT, MAX_PREVIEW_COUNT);
+                }
+            }
+            "theme" => {
+                if let Ok(theme) = Theme::try_from(value) {
+                    settings.theme = theme;
+                }
+            }
+            _ => (),
+        }
+    }
+
+    settings
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use super::{parse, Setting, Settings, Theme};
+    use crate::core::game_mode::GameMode;
+
+    #[test]
+    fn settings_survive_a_round_trip() {
+        let settings = Settings {
+            start_level: 9,
+            mode: GameMode::BType,
+            garbage_height: 3,
+            ghost: false,
+            das: Duration::from_millis(120),
+            arr: Duration::ZERO,
+            preview: 5,
+            theme: Theme::Ocean,
+        };
+
+        assert_eq!(settings, parse(&settings.serialise()));
+    }
+
+    #[test]
+    fn unreadable_values_keep_their_default() {
+        let settings = parse("start_level = lots\nmode = \"c-type\"\npreview = 40\nspeed = 3\n");
+
+        assert_eq!(0, settings.start_level);
+        assert_eq!(GameMode::AType, settings.mode);
+        assert_eq!(6, settings.preview);
+    }
+
+    #[test]
+    fn numbers_stop_at_the_end_of_their_range() {
+        let mut settings = Settings::default();
+
+        settings.adjust(Setting::StartLevel, -1);
+        settings.adjust(Setting::Preview, 10);
+        for _ in 0..10 {
+            settings.adjust(Setting::Arr, -1);
+        }
+
+        assert_eq!(0, settings.start_level);
+        assert_eq!(6, settings.preview);
+        assert_eq!(Duration::ZERO, settings.arr);
+    }
+
+    #[test]
+    fn choices_wrap_around() {
+        let mut settings = Settings::default();
+
+        settings.adjust(Setting::Theme, -1);
+        settings.adjust(Setting::Mode, -1);
+        settings.adjust(Setting::Ghost, 1);
+
+        assert_eq!(Theme::Mono, settings.theme);
+        assert_eq!(GameMode::Ultra, settings.mode);
+        assert!(!settings.ghost);
+    }
+
+    #[test]
+    fn copying_a_setting_leaves_the_others() {
+        let mut saved = Settings::default();
+        let in_play = Settings {
+            ghost: false,
+            das: Duration::from_millis(50),
+            ..Settings::default()
+        };
+
+        saved.copy_setting(Setting::Das, &in_play);
+
+        assert_eq!(Duration::from_millis(50), saved.das);
+        assert!(saved.ghost);
+    }
+}
@@ -14,7 +14,7 @@ pub(crate) enum TetronimoShape {
 
 impl Distribution<TetronimoShape> for StandardUniform {
     fn sample<R: rand::prelude::Rng + ?Sized>(&self, rng: &mut R) -> TetronimoShape {
-        match rng.random_range(0..=5) {
+        match rng.random_range(0..=6) {
             0 => TetronimoShape::IShape,
             1 => TetronimoShape::JShape,
             2 => TetronimoShape::LShape,
@@ -26,7 +26,13 @@ impl Distribution<TetronimoShape> for StandardUniform {
     }
 }
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum RotationDirection {
+    Clockwise,
+    CounterClockwise,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 enum TetronimoRotation {
     North,
     East,
@@ -35,12 +41,20 @@ enum TetronimoRotation {
 }
 
 impl TetronimoRotation {
-    fn next_rotation(&self) -> TetronimoRotation {
-        match self {
-            TetronimoRotation::North => TetronimoRotation::East,
-            TetronimoRotation::East => TetronimoRotation::South,
-            TetronimoRotation::South => TetronimoRotation::West,
-            TetronimoRotation::West => TetronimoRotation::North,
+    fn next_rotation(&self, direction: &RotationDirection) -> TetronimoRotation {
+        match direction {
+            RotationDirection::Clockwise => match self {
+                TetronimoRotation::North => TetronimoRotation::East,
+                TetronimoRotation::East => TetronimoRotation::South,
+                TetronimoRotation::South => TetronimoRotation::West,
+                TetronimoRotation::West => TetronimoRotation::North,
+            },
+            RotationDirection::CounterClockwise => match self {
+                TetronimoRotation::North => TetronimoRotation::West,
+                TetronimoRotation::West => TetronimoRotation::South,
+                TetronimoRotation::South => TetronimoRotation::East,
+                TetronimoRotation::East => TetronimoRotation::North,
+            },
         }
     }
 }
@@ -51,24 +65,84 @@ pub(crate) struct Tetronimo {
     rotation: TetronimoRotation,
 }
 
+// Every rotation of a shape is stored as the full bounding box used by the
+// Super Rotation System (https://tetris.wiki/Super_Rotation_System). Rotating
+// within a fixed box is what gives each shape its correct pivot, the I shape
+// turns around the centre of a 4x4 box while J, L, S, T and Z turn around the
+// centre cell of a 3x3 box.
+
 // const I_COLOR: char = '🟦';
 // const I_COLOR: char = 'I';
-const I_UP: [bool; 4] = [true, true, true, true];
-const I_RIGHT: [bool; 4] = [true, true, true, true];
+const I_UP: [bool; 16] = [
+    false, false, false, false,
+    true, true, true, true,
+    false, false, false, false,
+    false, false, false, false,
+];
+const I_RIGHT: [bool; 16] = [
+    false, false, true, false,
+    false, false, true, false,
+    false, false, true, false,
+    false, false, true, false,
+];
+const I_DOWN: [bool; 16] = [
+    false, false, false, false,
+    false, false, false, false,
+    true, true, true, true,
+    false, false, false, false,
+];
+const I_LEFT: [bool; 16] = [
+    false, true, false, false,
+    false, true, false, false,
+    false, true, false, false,
+    false, true, false, false,
+];
 
 // const J_COLOR: char = '🟪';
 // const J_COLOR: char = 'O';
-const J_UP: [bool; 6] = [true, true, true, false, false, true];
-const J_RIGHT: [bool; 6] = [false, true, false, true, true, true];
-const J_DOWN: [bool; 6] = [true, false, false, true, true, true];
-const J_LEFT: [bool; 6] = [true, true, true, false, true, false];
+const J_UP: [bool; 9] = [
+    true, false, false,
+    true, true, true,
+    false, false, false,
+];
+const J_RIGHT: [bool; 9] = [
+    false, true, true,
+    false, true, false,
+    false, true, false,
+];
+const J_DOWN: [bool; 9] = [
+    false, false, false,
+    true, true, true,
+    false, false, true,
+];
+const J_LEFT: [bool; 9] = [
+    false, true, false,
+    false, true, false,
+    true, true, false,
+];
 
 // const L_COLOR: char = '🟥';
 // const L_COLOR: char = 'L';
-const L_UP: [bool; 6] = [false, false, true, true, true, true];
-const L_RIGHT: [bool; 6] = [true, false, true, false, true, true];
-const L_DOWN: [bool; 6] = [true, true, true, true, false, false];
-const L_LEFT: [bool; 6] = [true, true, false, true, false, true];
+const L_UP: [bool; 9] = [
+    false, false, true,
+    true, true, true,
+    false, false, false,
+];
+const L_RIGHT: [bool; 9] = [
+    false, true, false,
+    false, true, false,
+    false, true, true,
+];
+const L_DOWN: [bool; 9] = [
+    false, false, false,
+    true, true, true,
+    true, false, false,
+];
+const L_LEFT: [bool; 9] = [
+    true, true, false,
+    false, true, false,
+    false, true, false,
+];
 
 // const O_COLOR: char = '🟨';
 // const O_COLOR: char = 'O';
@@ -76,20 +150,98 @@ const O_UP: [bool; 4] = [true, true, true, true];
 
 // const S_COLOR: char = '🟩';
 // const S_COLOR: char = 'S';
-const S_UP: [bool; 6] = [false, true, true, true, true, false];
-const S_RIGHT: [bool; 6] = [true, false, true, true, false, true];
+const S_UP: [bool; 9] = [
+    false, true, true,
+    true, true, false,
+    false, false, false,
+];
+const S_RIGHT: [bool; 9] = [
+    false, true, false,
+    false, true, true,
+    false, false, true,
+];
+const S_DOWN: [bool; 9] = [
+    false, false, false,
+    false, true, true,
+    true, true, false,
+];
+const S_LEFT: [bool; 9] = [
+    true, false, false,
+    true, true, false,
+    false, true, false,
+];
 
 // const T_COLOR: char = '🟫';
 // const T_COLOR: char = 'T';
-const T_UP: [bool; 6] = [false, true, false, true, true, true];
-const T_RIGHT: [bool; 6] = [true, false, true, true, true, false];
-const T_DOWN: [bool; 6] = [true, true, true, false, true, false];
-const T_LEFT: [bool; 6] = [false, true, true, true, false, true];
+const T_UP: [bool; 9] = [
+    false, true, false,
+    true, true, true,
+    false, false, false,
+];
+const T_RIGHT: [bool; 9] = [
+    false, true, false,
+    false, true, true,
+    false, true, false,
+];
+const T_DOWN: [bool; 9] = [
+    false, false, false,
+    true, true, true,
+    false, true, false,
+];
+const T_LEFT: [bool; 9] = [
+    false, true, false,
+    true, true, false,
+    false, true, false,
+];
 
 // const Z_COLOR: char = '🟧';
 // const Z_COLOR: char = 'Z';
-const Z_UP: [bool; 6] = [true, true, false, false, true, true];
-const Z_RIGHT: [bool; 6] = [false, true, true, true, true, false];
+const Z_UP: [bool; 9] = [
+    true, true, false,
+    false, true, true,
+    false, false, false,
+];
+const Z_RIGHT: [bool; 9] = [
+    false, false, true,
+    false, true, true,
+    false, true, false,
+];
+const Z_DOWN: [bool; 9] = [
+    false, false, false,
+    true, true, false,
+    false, true, true,
+];
+const Z_LEFT: [bool; 9] = [
+    false, true, false,
+    true, true, false,
+    true, false, false,
+];
+
+// Wall kick offsets are written exactly as they appear in the SRS tables, so
+// x grows to the right and y grows *upwards*. Each rotation is attempted with
+// every offset in turn and the first one that fits is used.
+type WallKicks = [(isize, isize); 5];
+
+const JLSTZ_NORTH_EAST: WallKicks = [(0, 0), (-1, 0), (-1, 1), (0, -2), (-1, -2)];
+const JLSTZ_EAST_NORTH: WallKicks = [(0, 0), (1, 0), (1, -1), (0, 2), (1, 2)];
+const JLSTZ_EAST_SOUTH: WallKicks = [(0, 0), (1, 0), (1, -1), (0, 2), (1, 2)];
+const JLSTZ_SOUTH_EAST: WallKicks = [(0, 0), (-1, 0), (-1, 1), (0, -2), (-1, -2)];
+const JLSTZ_SOUTH_WEST: WallKicks = [(0, 0), (1, 0), (1, 1), (0, -2), (1, -2)];
+const JLSTZ_WEST_SOUTH: WallKicks = [(0, 0), (-1, 0), (-1, -1), (0, 2), (-1, 2)];
+const JLSTZ_WEST_NORTH: WallKicks = [(0, 0), (-1, 0), (-1, -1), (0, 2), (-1, 2)];
+const JLSTZ_NORTH_WEST: WallKicks = [(0, 0), (1, 0), (1, 1), (0, -2), (1, -2)];
+
+const I_NORTH_EAST: WallKicks = [(0, 0), (-2, 0), (1, 0), (-2, -1), (1, 2)];
+const I_EAST_NORTH: WallKicks = [(0, 0), (2, 0), (-1, 0), (2, 1), (-1, -2)];
+const I_EAST_SOUTH: WallKicks = [(0, 0), (-1, 0), (2, 0), (-1, 2), (2, -1)];
+const I_SOUTH_EAST: WallKicks = [(0, 0), (1, 0), (-2, 0), (1, -2), (-2, 1)];
+const I_SOUTH_WEST: WallKicks = [(0, 0), (2, 0), (-1, 0), (2, 1), (-1, -2)];
+const I_WEST_SOUTH: WallKicks = [(0, 0), (-2, 0), (1, 0), (-2, -1), (1, 2)];
+const I_WEST_NORTH: WallKicks = [(0, 0), (1, 0), (-2, 0), (1, -2), (-2, 1)];
+const I_NORTH_WEST: WallKicks = [(0, 0), (-1, 0), (2, 0), (-1, 2), (2, -1)];
+
+// The O shape never kicks, it just stays where it is.
+const O_KICKS: [(isize, isize); 1] = [(0, 0)];
 
 impl Tetronimo {
     pub(crate) fn new(shape: TetronimoShape) -> Self {
@@ -99,33 +251,25 @@ impl Tetronimo {
         }
     }
 
-    pub(crate) fn random() -> Self {
-        Self {
-            // shape: TetronimoShape::TShape,
-            shape: rand::random(),
-            rotation: TetronimoRotation::North,
-        }
-    }
-
     fn get_shape_with_rotation(shape: &TetronimoShape, rotation: &TetronimoRotation) -> (&'static[bool], usize) {
         match shape {
             TetronimoShape::IShape => match rotation {
-                TetronimoRotation::North => (&I_UP, 1),
+                TetronimoRotation::North => (&I_UP, 4),
                 TetronimoRotation::East => (&I_RIGHT, 4),
-                TetronimoRotation::South => (&I_UP, 1),
-                TetronimoRotation::West => (&I_RIGHT, 4),
+                TetronimoRotation::South => (&I_DOWN, 4),
+                TetronimoRotation::West => (&I_LEFT, 4),
             },
             TetronimoShape::JShape => match rotation {
                 TetronimoRotation::North => (&J_UP, 3),
-                TetronimoRotation::East => (&J_RIGHT, 2),
+                TetronimoRotation::East => (&J_RIGHT, 3),
                 TetronimoRotation::South => (&J_DOWN, 3),
-                TetronimoRotation::West => (&J_LEFT, 2),
+                TetronimoRotation::West => (&J_LEFT, 3),
             },
             TetronimoShape::LShape => match rotation {
                 TetronimoRotation::North => (&L_UP, 3),
-                TetronimoRotation::East => (&L_RIGHT, 2),
+                TetronimoRotation::East => (&L_RIGHT, 3),
                 TetronimoRotation::South => (&L_DOWN, 3),
-                TetronimoRotation::West => (&L_LEFT, 2),
+                TetronimoRotation::West => (&L_LEFT, 3),
             },
             TetronimoShape::OShape => match rotation {
                 TetronimoRotation::North => (&O_UP, 2),
@@ -135,21 +279,55 @@ impl Tetronimo {
             },
             TetronimoShape::SShape => match rotation {
                 TetronimoRotation::North => (&S_UP, 3),
-                TetronimoRotation::East => (&S_RIGHT, 2),
-                TetronimoRotation::South => (&S_UP, 3),
-                TetronimoRotation::West => (&S_RIGHT, 2),
+                TetronimoRotation::East => (&S_RIGHT, 3),
+                TetronimoRotation::South => (&S_DOWN, 3),
+                TetronimoRotation::West => (&S_LEFT, 3),
             },
             TetronimoShape::TShape => match rotation {
                 TetronimoRotation::North => (&T_UP, 3),
-                TetronimoRotation::East => (&T_RIGHT, 2),
+                TetronimoRotation::East => (&T_RIGHT, 3),
                 TetronimoRotation::South => (&T_DOWN, 3),
-                TetronimoRotation::West => (&T_LEFT, 2),
+                TetronimoRotation::West => (&T_LEFT, 3),
             },
             TetronimoShape::ZShape => match rotation {
                 TetronimoRotation::North => (&Z_UP, 3),
-                TetronimoRotation::East => (&Z_RIGHT, 2),
-                TetronimoRotation::South => (&Z_UP, 3),
-                TetronimoRotation::West => (&Z_RIGHT, 2),
+                TetronimoRotation::East => (&Z_RIGHT, 3),
+                TetronimoRotation::South => (&Z_DOWN, 3),
+                TetronimoRotation::West => (&Z_LEFT, 3),
+            },
+        }
+    }
+
+    fn get_wall_kicks_for_rotation(
+        shape: &TetronimoShape,
+        from: &TetronimoRotation,
+        to: &TetronimoRotation,
+    ) -> &'static [(isize, isize)] {
+        use TetronimoRotation::{East, North, South, West};
+
+        match shape {
+            TetronimoShape::OShape => &O_KICKS,
+            TetronimoShape::IShape => match (from, to) {
+                (North, East) => &I_NORTH_EAST,
+                (East, North) => &I_EAST_NORTH,
+                (East, South) => &I_EAST_SOUTH,
+                (South, East) => &I_SOUTH_EAST,
+                (South, West) => &I_SOUTH_WEST,
+                (West, South) => &I_WEST_SOUTH,
+                (West, North) => &I_WEST_NORTH,
+                (North, West) => &I_NORTH_WEST,
+                _ => &O_KICKS,
+            },
+            _ => match (from, to) {
+                (North, East) => &JLSTZ_NORTH_EAST,
+                (East, North) => &JLSTZ_EAST_NORTH,
+                (East, South) => &JLSTZ_EAST_SOUTH,
+                (South, East) => &JLSTZ_SOUTH_EAST,
+                (South, West) => &JLSTZ_SOUTH_WEST,
+                (West, South) => &JLSTZ_WEST_SOUTH,
+                (West, North) => &JLSTZ_WEST_NORTH,
+                (North, West) => &JLSTZ_NORTH_WEST,
+                _ => &O_KICKS,
             },
         }
     }
@@ -158,11 +336,202 @@ impl Tetronimo {
         Self::get_shape_with_rotation(&self.shape, &self.rotation)
     }
 
-    pub(crate) fn rotate(&mut self) {
-        self.rotation = self.rotation.next_rotation();
+    pub(crate) fn rotate(&mut self, direction: &RotationDirection) {
+        self.rotation = self.rotation.next_rotation(direction);
+    }
+
+    pub(crate) fn get_next_rotation_chars(&self, direction: &RotationDirection) -> (&[bool], usize) {
+        Self::get_shape_with_rotation(&self.shape, &self.rotation.next_rotation(direction))
+    }
+
+    pub(crate) fn get_wall_kicks(&self, direction: &RotationDirection) -> &'static [(isize, isize)] {
+        Self::get_wall_kicks_for_rotation(
+            &self.shape,
+            &self.rotation,
+            &self.rotation.next_rotation(direction),
+        )
+    }
+
+    // The two corners of a 3x3 box on the side the piece points towards,
+    // used to tell a T-spin from a mini T-spin.
+    pub(crate) fn front_corners(&self) -> [(isize, isize); 2] {
+        match self.rotation {
+            TetronimoRotation::North => [(0, 0), (2, 0)],
+            TetronimoRotation::East => [(2, 0), (2, 2)],
+            TetronimoRotation::South => [(0, 2), (2, 2)],
+            TetronimoRotation::West => [(0, 0), (0, 2)],
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{RotationDirection, Tetronimo, TetronimoRotation, TetronimoShape, WallKicks};
+
+    const SHAPES: [TetronimoShape; 7] = [
+        TetronimoShape::IShape,
+        TetronimoShape::JShape,
+        TetronimoShape::LShape,
+        TetronimoShape::OShape,
+        TetronimoShape::SShape,
+        TetronimoShape::TShape,
+        TetronimoShape::ZShape,
+    ];
+
+    const TRANSITIONS: [(TetronimoRotation, RotationDirection, TetronimoRotation); 8] = [
+        (TetronimoRotation::North, RotationDirection::Clockwise, TetronimoRotation::East),
+        (TetronimoRotation::East, RotationDirection::Clockwise, TetronimoRotation::South),
+        (TetronimoRotation::South, RotationDirection::Clockwise, TetronimoRotation::West),
+        (TetronimoRotation::West, RotationDirection::Clockwise, TetronimoRotation::North),
+        (TetronimoRotation::North, RotationDirection::CounterClockwise, TetronimoRotation::West),
+        (TetronimoRotation::West, RotationDirection::CounterClockwise, TetronimoRotation::South),
+        (TetronimoRotation::South, RotationDirection::CounterClockwise, TetronimoRotation::East),
+        (TetronimoRotation::East, RotationDirection::CounterClockwise, TetronimoRotation::North),
+    ];
+
+    // Turn a square mask a quarter turn around the centre of its box.
+    fn rotate_mask(mask: &[bool], width: usize, direction: &RotationDirection) -> Vec<bool> {
+        let mut rotated = vec![false; mask.len()];
+        for y in 0..width {
+            for x in 0..width {
+                let (from_x, from_y) = match direction {
+                    RotationDirection::Clockwise => (y, width - 1 - x),
+                    RotationDirection::CounterClockwise => (width - 1 - y, x),
+                };
+                rotated[(y * width) + x] = mask[(from_y * width) + from_x];
+            }
+        }
+        rotated
+    }
+
+    #[test]
+    fn every_rotation_has_four_blocks() {
+        for shape in SHAPES {
+            for (from, _, _) in TRANSITIONS {
+                let (mask, width) = Tetronimo::get_shape_with_rotation(&shape, &from);
+                assert_eq!(width * width, mask.len(), "{shape:?} {from:?}");
+                assert_eq!(4, mask.iter().filter(|present| **present).count(), "{shape:?} {from:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn rotation_turns_around_pivot() {
+        for shape in SHAPES {
+            for (from, direction, to) in TRANSITIONS {
+                let (mask, width) = Tetronimo::get_shape_with_rotation(&shape, &from);
+                let (expected, _) = Tetronimo::get_shape_with_rotation(&shape, &to);
+
+                assert_eq!(
+                    expected,
+                    rotate_mask(mask, width, &direction).as_slice(),
+                    "{shape:?} {from:?} -> {to:?}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn rotation_transitions() {
+        for (from, direction, to) in TRANSITIONS {
+            assert_eq!(to, from.next_rotation(&direction));
+        }
+    }
+
+    #[test]
+    fn clockwise_then_counter_clockwise_is_identity() {
+        for shape in SHAPES {
+            let mut under_test = Tetronimo::new(shape.clone());
+            for _ in 0..4 {
+                let before = under_test.rotation;
+                under_test.rotate(&RotationDirection::Clockwise);
+                under_test.rotate(&RotationDirection::CounterClockwise);
+                assert_eq!(before, under_test.rotation, "{shape:?}");
+                under_test.rotate(&RotationDirection::Clockwise);
+            }
+        }
+    }
+
+    #[test]
+    fn wall_kicks_start_with_basic_rotation() {
+        for shape in SHAPES {
+            for (from, _, to) in TRANSITIONS {
+                let kicks = Tetronimo::get_wall_kicks_for_rotation(&shape, &from, &to);
+                assert_eq!((0, 0), kicks[0], "{shape:?} {from:?} -> {to:?}");
+            }
+        }
     }
 
-    pub (crate) fn get_next_rotation_chars(&self) -> (&[bool], usize) {
-        Self::get_shape_with_rotation(&self.shape, &self.rotation.next_rotation())
+    #[test]
+    fn wall_kicks_reverse_transition_is_negated() {
+        for shape in SHAPES {
+            for (from, _, to) in TRANSITIONS {
+                let forward = Tetronimo::get_wall_kicks_for_rotation(&shape, &from, &to);
+                let reverse = Tetronimo::get_wall_kicks_for_rotation(&shape, &to, &from);
+                let negated: Vec<(isize, isize)> = reverse.iter().map(|(x, y)| (-x, -y)).collect();
+
+                assert_eq!(forward, negated.as_slice(), "{shape:?} {from:?} -> {to:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn wall_kicks_match_srs_tables() {
+        let cases: [(TetronimoShape, TetronimoRotation, TetronimoRotation, WallKicks); 6] = [
+            (
+                TetronimoShape::TShape,
+                TetronimoRotation::North,
+                TetronimoRotation::East,
+                [(0, 0), (-1, 0), (-1, 1), (0, -2), (-1, -2)],
+            ),
+            (
+                TetronimoShape::JShape,
+                TetronimoRotation::East,
+                TetronimoRotation::South,
+                [(0, 0), (1, 0), (1, -1), (0, 2), (1, 2)],
+            ),
+            (
+                TetronimoShape::ZShape,
+                TetronimoRotation::West,
+                TetronimoRotation::North,
+                [(0, 0), (-1, 0), (-1, -1), (0, 2), (-1, 2)],
+            ),
+            (
+                TetronimoShape::IShape,
+                TetronimoRotation::North,
+                TetronimoRotation::East,
+                [(0, 0), (-2, 0), (1, 0), (-2, -1), (1, 2)],
+            ),
+            (
+                TetronimoShape::IShape,
+                TetronimoRotation::East,
+                TetronimoRotation::South,
+                [(0, 0), (-1, 0), (2, 0), (-1, 2), (2, -1)],
+            ),
+            (
+                TetronimoShape::IShape,
+                TetronimoRotation::North,
+                TetronimoRotation::West,
+                [(0, 0), (-1, 0), (2, 0), (-1, 2), (2, -1)],
+            ),
+        ];
+
+        for (shape, from, to, expected) in cases {
+            assert_eq!(
+                &expected,
+                Tetronimo::get_wall_kicks_for_rotation(&shape, &from, &to),
+                "{shape:?} {from:?} -> {to:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn o_shape_never_kicks() {
+        for (from, _, to) in TRANSITIONS {
+            assert_eq!(
+                &[(0, 0)],
+                Tetronimo::get_wall_kicks_for_rotation(&TetronimoShape::OShape, &from, &to)
+            );
+        }
     }
 }
@@ -1,4 +1,5 @@
 mod core;
+mod options;
 mod widgets;
 
 use anathema::{
@@ -7,15 +8,24 @@ use anathema::{
     widgets::components::events::KeyState,
 };
 use core::{
-    game_loop::GameLoop,
+    auto_shift::AutoShift,
+    game_loop::{GameLoop, DEFAULT_HIDDEN_ROWS},
     global_state::{self, GameStateComponentIds, GlobalStateManagementMessage},
+    held_key::HeldKey,
+    high_scores::{self, HighScores},
+    keymap,
+    settings::{self, Settings},
 };
 use anathema::component::Event;
+use options::{Options, OptionsError, USAGE};
 use widgets::{
-    game::{GameComponent, GameComponentState},
+    callout::{CalloutComponent, CalloutState},
+    game::{self, GameComponent, GameComponentState},
     game_arena::{GameArenaComponent, GameArenaComponentState},
     game_over::{GameOverComponent, GameOverComponentState},
     game_type::{GameTypeComponent, GameTypeState},
+    hold::{HoldComponent, HoldState},
+    level::{LevelComponent, LevelState},
     line_count::{LineCountComponent, LineCountState},
     main_menu::{MainMenuComponent, MainMenuComponentState},
     next_piece::{NextPieceComponent, NextPieceState},
@@ -23,11 +33,62 @@ use widgets::{
     static_piece::{StaticPieceComponent, StaticPieceState},
     statistic::{StatisticComponent, StatisticComponentState},
     statistics::{StatisticsComponent, StatisticsState},
+    streaks::{StreaksComponent, StreaksState},
+    timer::{TimerComponent, TimerState},
 };
 
 fn main() {
+    let settings_path = settings::default_path();
+    let saved_settings = settings::load(settings_path.as_deref());
+    let options = match Options::from_args(std::env::args().skip(1), (&saved_settings).into()) {
+        Ok(options) => options,
+        Err(OptionsError::Help) => {
+            println!("{USAGE}");
+            std::process::exit(0);
+        }
+        Err(OptionsError::Invalid(error)) => {
+            eprintln!("{error}");
+            std::process::exit(1);
+        }
+    };
+    let keymap = match keymap::load(options.keys.clone()) {
+        Ok(keymap) => keymap,
+        Err(error) => {
+            eprintln!("{error}");
+            std::process::exit(1);
+        }
+    };
+
+    // Overrides from the command line only last for this run, they are kept
+    // out of the settings the menu saves.
+    let settings = Settings {
+        ghost: options.ghost,
+        das: options.das,
+        arr: options.arr,
+        preview: options.preview.unwrap_or(saved_settings.preview),
+        ..saved_settings
+    };
+
     let (tx, rx) = smol::channel::unbounded::<GlobalStateManagementMessage>();
-    let game_loop = GameLoop::new(10, 20, tx.clone());
+    let game_loop = GameLoop::new(options.width, options.height, tx.clone())
+        .with_hidden_rows(DEFAULT_HIDDEN_ROWS)
+        .with_piece_generator(options.randomizer)
+        .with_gravity_curve(options.gravity)
+        .with_scoring_system(options.scoring)
+        .with_ultra_duration(options.ultra_time)
+        .with_lock_delay(options.lock_delay)
+        .with_move_reset_limit(options.lock_resets)
+        .with_line_clear_ticks(options.clear_ticks)
+        .with_line_clear_gravity(options.clear_gravity)
+        .with_preview_count(settings.preview)
+        .with_seed(options.seed);
+
+    // Checked before the backend takes over the terminal, so the error is
+    // printed to a terminal that is still usable.
+    if let Err(error) = check_terminal_size(&options, settings.preview) {
+        eprintln!("{error}");
+        std::process::exit(1);
+    }
 
     let mut backend = TuiBackend::builder()
         .clear()
@@ -53,8 +114,8 @@ fn main() {
         .component(
             "MainMenu",
             "src/templates/main_menu.aml",
-            MainMenuComponent::new(tx.clone()),
-            MainMenuComponentState::new(),
+            MainMenuComponent::new(tx.clone(), settings, saved_settings),
+            MainMenuComponentState::new(&settings),
         )
         .unwrap();
 
@@ -63,7 +124,7 @@ fn main() {
             "Game",
             "src/templates/game.aml",
             GameComponent {},
-            GameComponentState::new(),
+            GameComponentState::new(settings.theme, options.width, options.height),
         )
         .unwrap();
 
@@ -85,6 +146,15 @@ fn main() {
         )
         .unwrap();
 
+    let hold_id = builder
+        .component(
+            "Hold",
+            "src/templates/hold.aml",
+            HoldComponent {},
+            HoldState::new(),
+        )
+        .unwrap();
+
     let statistics_id = builder
         .component(
             "Statistics",
@@ -112,12 +182,48 @@ fn main() {
         )
         .unwrap();
 
-    builder
+    let level_id = builder
+        .component(
+            "Level",
+            "src/templates/level.aml",
+            LevelComponent {},
+            LevelState::new(),
+        )
+        .unwrap();
+
+    let game_type_id = builder
         .component(
             "GameType",
             "src/templates/game_type.aml",
             GameTypeComponent {},
-            GameTypeState {},
+            GameTypeState::new(),
+        )
+        .unwrap();
+
+    let timer_id = builder
+        .component(
+            "Timer",
+            "src/templates/timer.aml",
+            TimerComponent {},
+            TimerState::new(),
+        )
+        .unwrap();
+
+    let callout_id = builder
+        .component(
+            "Callout",
+            "src/templates/callout.aml",
+            CalloutComponent::new(),
+            CalloutState::new(),
+        )
+        .unwrap();
+
+    let streaks_id = builder
+        .component(
+            "Streaks",
+            "src/templates/streaks.aml",
+            StreaksComponent {},
+            StreaksState::new(),
         )
         .unwrap();
 
@@ -125,7 +231,13 @@ fn main() {
         .component(
             "GameArena",
             "src/templates/game_arena.aml",
-            GameArenaComponent::new(tx.clone(), game_loop),
+            GameArenaComponent::new(
+                tx.clone(),
+                game_loop,
+                settings.ghost,
+                AutoShift::new(settings.das, settings.arr).with_key_hold(options.key_hold),
+                HeldKey::new(options.key_hold),
+            ),
             GameArenaComponentState::new(),
         )
         .unwrap();
@@ -160,16 +272,48 @@ fn main() {
         game_over_id,
         score_board_id,
         lines_count_id,
+        level_id,
+        game_type_id,
         next_piece_id,
+        hold_id,
         statistics_id,
+        timer_id,
+        callout_id,
+        streaks_id,
     );
 
-    global_state::start(emitter, tx.clone(), rx, component_ids);
+    let high_scores = HighScores::load(high_scores::default_path());
+    global_state::start(
+        emitter,
+        tx.clone(),
+        rx,
+        component_ids,
+        high_scores,
+        keymap,
+        settings_path,
+    );
     builder
         .finish(&mut backend, |runtime, backend| runtime.run(backend))
         .unwrap();
 }
 
+// A terminal that can't report its size is left for the backend to deal with.
+fn check_terminal_size(options: &Options, preview_count: usize) -> Result<(), String> {
+    let Ok((width, height)) = crossterm::terminal::size() else {
+        return Ok(());
+    };
+    let (required_width, required_height) =
+        game::required_terminal_size(options.width, options.height, preview_count);
+
+    if width < required_width || height < required_height {
+        return Err(format!(
+            "the terminal is {width}x{height} but a {}x{} arena needs at least {required_width}x{required_height}, make the terminal bigger or use a smaller --width or --height",
+            options.width, options.height
+        ));
+    }
+    Ok(())
+}
+
 fn check_for_exit(
     event: &Event,
 ) -> Option<Event> {
@@ -0,0 +1,466 @@
+use std::{path::PathBuf, time::Duration};
+
+use crate::core::{
+    auto_shift::{DEFAULT_ARR, DEFAULT_DAS},
+    game_loop::{
+        DEFAULT_ARENA_HEIGHT, DEFAULT_ARENA_WIDTH, DEFAULT_LINE_CLEAR_TICKS, DEFAULT_LOCK_DELAY,
+        DEFAULT_MOVE_RESET_LIMIT, DEFAULT_ULTRA_DURATION, MAX_ARENA_HEIGHT, MAX_ARENA_WIDTH,
+        MAX_PREVIEW_COUNT, MIN_ARENA_HEIGHT, MIN_ARENA_WIDTH, MIN_PREVIEW_COUNT,
+    },
+    gravity::{GravityCurve, LineClearGravity},
+    held_key::DEFAULT_KEY_HOLD,
+    piece_generator::PieceGeneratorType,
+    scoring::ScoringSystemType,
+    settings::Settings,
+};
+
+pub(crate) const USAGE: &str = "usage: anatris [options]
+
+options:
+    --randomizer <bag|nes|uniform>    how the next piece is picked
+    --seed <number>                   play the piece sequence for a seed
+    --width <4-20>                    columns in the arena, 10 by default
+    --height <10-40>                  rows in the arena, 20 by default
+    --preview <1-6>                   number of next pieces to show
+    --ghost <on|off>                  show where the piece will land
+    --gravity <nes|guideline>         how quickly pieces fall per level
+    --scoring <nes|guideline>         how points are awarded
+    --ultra-time <seconds>            length of an ultra game, 120 by default
+    --lock-delay <ms>                 time a landed piece waits before locking
+    --lock-resets <number|infinite>   moves that restart the lock delay, 15 by default
+    --das <ms>                        how long to hold left or right before it repeats
+    --arr <ms>                        time between repeated moves, 0 moves to the wall
+    --key-hold <ms>                   how long a key counts as held before the terminal repeats it
+    --clear-ticks <number>            frames a line clear is shown for, 0 turns it off
+    --clear-gravity <naive|cascade>   how blocks fall after a line clear
+    --keys <path>                     key bindings file, ~/.config/anatris/keys.toml by default";
+
+// Asking for help isn't a mistake, so it is kept apart from arguments that
+// couldn't be used.
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum OptionsError {
+    Help,
+    Invalid(String),
+}
+
+impl From<String> for OptionsError {
+    fn from(error: String) -> Self {
+        OptionsError::Invalid(error)
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) struct Options {
+    pub(crate) randomizer: PieceGeneratorType,
+    pub(crate) seed: Option<u64>,
+    pub(crate) width: usize,
+    pub(crate) height: usize,
+    pub(crate) preview: Option<usize>,
+    pub(crate) ghost: bool,
+    pub(crate) gravity: GravityCurve,
+    pub(crate) scoring: ScoringSystemType,
+    pub(crate) ultra_time: Duration,
+    pub(crate) lock_delay: Duration,
+    pub(crate) lock_resets: Option<u16>,
+    pub(crate) das: Duration,
+    pub(crate) arr: Duration,
+    pub(crate) key_hold: Duration,
+    pub(crate) clear_ticks: u16,
+    pub(crate) clear_gravity: LineClearGravity,
+    pub(crate) keys: Option<PathBuf>,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            randomizer: PieceGeneratorType::default(),
+            seed: None,
+            width: DEFAULT_ARENA_WIDTH,
+            height: DEFAULT_ARENA_HEIGHT,
+            preview: None,
+            ghost: true,
+            gravity: GravityCurve::default(),
+            scoring: ScoringSystemType::default(),
+            ultra_time: DEFAULT_ULTRA_DURATION,
+            lock_delay: DEFAULT_LOCK_DELAY,
+            lock_resets: Some(DEFAULT_MOVE_RESET_LIMIT),
+            das: DEFAULT_DAS,
+            arr: DEFAULT_ARR,
+            key_hold: DEFAULT_KEY_HOLD,
+            clear_ticks: DEFAULT_LINE_CLEAR_TICKS,
+            clear_gravity: LineClearGravity::default(),
+            keys: None,
+        }
+    }
+}
+
+// Saved settings are the starting point, anything given on the command line
+// overrides them for this run.
+impl From<&Settings> for Options {
+    fn from(settings: &Settings) -> Self {
+        Self {
+            preview: Some(settings.preview),
+            ghost: settings.ghost,
+            das: settings.das,
+            arr: settings.arr,
+            ..Options::default()
+        }
+    }
+}
+
+impl Options {
+    pub(crate) fn from_args<I>(args: I, defaults: Options) -> Result<Self, OptionsError>
+    where
+        I: IntoIterator<Item = String>,
+    {
+        let mut options = defaults;
+        let mut args = args.into_iter();
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--randomizer" => {
+                    let value = args
+                        .next()
+                        .ok_or_else(|| format!("--randomizer needs a value\n{USAGE}"))?;
+                    options.randomizer = value.as_str().try_into()?;
+                }
+                "--seed" => {
+                    let value = args
+                        .next()
+                        .ok_or_else(|| format!("--seed needs a value\n{USAGE}"))?;
+                    let seed = value
+                        .parse()
+                        .map_err(|_| format!("invalid seed '{value}', expected a positive number"))?;
+                    options.seed = Some(seed);
+                }
+                "--width" => {
+                    let value = args
+                        .next()
+                        .ok_or_else(|| format!("--width needs a value\n{USAGE}"))?;
+                    options.width = parse_size("width", &value, MIN_ARENA_WIDTH, MAX_ARENA_WIDTH)?;
+                }
+                "--height" => {
+                    let value = args
+                        .next()
+                        .ok_or_else(|| format!("--height needs a value\n{USAGE}"))?;
+                    options.height =
+                        parse_size("height", &value, MIN_ARENA_HEIGHT, MAX_ARENA_HEIGHT)?;
+                }
+                "--preview" => {
+                    let value = args
+                        .next()
+                        .ok_or_else(|| format!("--preview needs a value\n{USAGE}"))?;
+                    let preview = value
+                        .parse()
+                        .ok()
+                        .filter(|count| (MIN_PREVIEW_COUNT..=MAX_PREVIEW_COUNT).contains(count))
+                        .ok_or_else(|| {
+                            format!(
+                                "invalid preview count '{value}', expected a number from {MIN_PREVIEW_COUNT} to {MAX_PREVIEW_COUNT}"
+                            )
+                        })?;
+                    options.preview = Some(preview);
+                }
+                "--gravity" => {
+                    let value = args
+                        .next()
+                        .ok_or_else(|| format!("--gravity needs a value\n{USAGE}"))?;
+                    options.gravity = value.as_str().try_into()?;
+                }
+                "--scoring" => {
+                    let value = args
+                        .next()
+                        .ok_or_else(|| format!("--scoring needs a value\n{USAGE}"))?;
+                    options.scoring = value.as_str().try_into()?;
+                }
+                "--ultra-time" => {
+                    let value = args
+                        .next()
+                        .ok_or_else(|| format!("--ultra-time needs a value\n{USAGE}"))?;
+                    let seconds = value.parse().ok().filter(|seconds| *seconds > 0).ok_or_else(
+                        || format!("invalid ultra time '{value}', expected a number of seconds"),
+                    )?;
+                    options.ultra_time = Duration::from_secs(seconds);
+                }
+                "--lock-delay" => {
+                    let value = args
+                        .next()
+                        .ok_or_else(|| format!("--lock-delay needs a value\n{USAGE}"))?;
+                    options.lock_delay = parse_millis("lock delay", &value)?;
+                }
+                "--lock-resets" => {
+                    let value = args
+                        .next()
+                        .ok_or_else(|| format!("--lock-resets needs a value\n{USAGE}"))?;
+                    options.lock_resets = match value.as_str() {
+                        "infinite" => None,
+                        _ => Some(value.parse().map_err(|_| {
+                            format!("invalid lock resets '{value}', expected a number or infinite")
+                        })?),
+                    };
+                }
+                "--das" => {
+                    let value = args
+                        .next()
+                        .ok_or_else(|| format!("--das needs a value\n{USAGE}"))?;
+                    options.das = parse_millis("das", &value)?;
+                }
+                "--arr" => {
+                    let value = args
+                        .next()
+                        .ok_or_else(|| format!("--arr needs a value\n{USAGE}"))?;
+                    options.arr = parse_millis("arr", &value)?;
+                }
+                "--key-hold" => {
+                    let value = args
+                        .next()
+                        .ok_or_else(|| format!("--key-hold needs a value\n{USAGE}"))?;
+                    options.key_hold = parse_millis("key hold", &value)?;
+                }
+                "--clear-ticks" => {
+                    let value = args
+                        .next()
+                        .ok_or_else(|| format!("--clear-ticks needs a value\n{USAGE}"))?;
+                    options.clear_ticks = value.parse().map_err(|_| {
+                        format!("invalid clear ticks '{value}', expected a number of frames")
+                    })?;
+                }
+                "--clear-gravity" => {
+                    let value = args
+                        .next()
+                        .ok_or_else(|| format!("--clear-gravity needs a value\n{USAGE}"))?;
+                    options.clear_gravity = value.as_str().try_into()?;
+                }
+                "--keys" => {
+                    let value = args
+                        .next()
+                        .ok_or_else(|| format!("--keys needs a value\n{USAGE}"))?;
+                    options.keys = Some(PathBuf::from(value));
+                }
+                "--ghost" => {
+                    let value = args
+                        .next()
+                        .ok_or_else(|| format!("--ghost needs a value\n{USAGE}"))?;
+                    options.ghost = match value.as_str() {
+                        "on" => true,
+                        "off" => false,
+                        _ => {
+                            return Err(format!(
+                                "invalid ghost setting '{value}', expected on or off"
+                            )
+                            .into())
+                        }
+                    };
+                }
+                "--help" | "-h" => return Err(OptionsError::Help),
+                _ => return Err(format!("unknown argument '{arg}'\n{USAGE}").into()),
+            }
+        }
+
+        Ok(options)
+    }
+}
+
+fn parse_size(name: &str, value: &str, min: usize, max: usize) -> Result<usize, String> {
+    value
+        .parse()
+        .ok()
+        .filter(|size| (min..=max).contains(size))
+        .ok_or_else(|| format!("invalid {name} '{value}', expected a number from {min} to {max}"))
+}
+
+fn parse_millis(name: &str, value: &str) -> Result<Duration, String> {
+    value
+        .parse()
+        .map(Duration::from_millis)
+        .map_err(|_| format!("invalid {name} '{value}', expected a number of milliseconds"))
+}
+
+#[cfg(test)]
+mod test {
+    use std::{path::PathBuf, time::Duration};
+
+    use super::{Options, OptionsError};
+    use crate::core::{
+        gravity::{GravityCurve, LineClearGravity},
+        piece_generator::PieceGeneratorType,
+        scoring::ScoringSystemType,
+        settings::Settings,
+    };
+
+    fn parse(args: &[&str]) -> Result<Options, OptionsError> {
+        Options::from_args(args.iter().map(|arg| arg.to_string()), Options::default())
+    }
+
+    #[test]
+    fn defaults_without_arguments() {
+        assert_eq!(Ok(Options::default()), parse(&[]));
+    }
+
+    #[test]
+    fn arguments_override_saved_settings() {
+        let settings = Settings {
+            ghost: false,
+            preview: 5,
+            ..Settings::default()
+        };
+
+        let options =
+            Options::from_args(["--preview".to_string(), "2".to_string()], (&settings).into())
+                .unwrap();
+
+        assert!(!options.ghost);
+        assert_eq!(Some(2), options.preview);
+    }
+
+    #[test]
+    fn parse_randomizer() {
+        let options = parse(&["--randomizer", "nes"]).unwrap();
+        assert_eq!(PieceGeneratorType::Nes, options.randomizer);
+    }
+
+    #[test]
+    fn parse_seed() {
+        let options = parse(&["--seed", "1234", "--randomizer", "uniform"]).unwrap();
+        assert_eq!(Some(1234), options.seed);
+        assert_eq!(PieceGeneratorType::Uniform, options.randomizer);
+    }
+
+    #[test]
+    fn parse_arena_size() {
+        let options = parse(&[]).unwrap();
+        assert_eq!((10, 20), (options.width, options.height));
+
+        let options = parse(&["--width", "4", "--height", "40"]).unwrap();
+        assert_eq!((4, 40), (options.width, options.height));
+    }
+
+    #[test]
+    fn parse_preview() {
+        let options = parse(&["--preview", "6"]).unwrap();
+        assert_eq!(Some(6), options.preview);
+    }
+
+    #[test]
+    fn parse_ghost() {
+        assert!(parse(&[]).unwrap().ghost);
+        assert!(!parse(&["--ghost", "off"]).unwrap().ghost);
+        assert!(parse(&["--ghost", "on"]).unwrap().ghost);
+    }
+
+    #[test]
+    fn parse_gravity() {
+        assert_eq!(GravityCurve::Nes, parse(&[]).unwrap().gravity);
+        assert_eq!(
+            GravityCurve::Guideline,
+            parse(&["--gravity", "guideline"]).unwrap().gravity
+        );
+    }
+
+    #[test]
+    fn parse_scoring() {
+        assert_eq!(ScoringSystemType::Guideline, parse(&[]).unwrap().scoring);
+        assert_eq!(ScoringSystemType::Nes, parse(&["--scoring", "nes"]).unwrap().scoring);
+    }
+
+    #[test]
+    fn parse_ultra_time() {
+        assert_eq!(Duration::from_secs(120), parse(&[]).unwrap().ultra_time);
+        assert_eq!(
+            Duration::from_secs(180),
+            parse(&["--ultra-time", "180"]).unwrap().ultra_time
+        );
+    }
+
+    #[test]
+    fn parse_lock_delay() {
+        let options = parse(&[]).unwrap();
+        assert_eq!(Duration::from_millis(500), options.lock_delay);
+        assert_eq!(Some(15), options.lock_resets);
+
+        let options = parse(&["--lock-delay", "0", "--lock-resets", "infinite"]).unwrap();
+        assert_eq!(Duration::ZERO, options.lock_delay);
+        assert_eq!(None, options.lock_resets);
+
+        assert_eq!(Some(3), parse(&["--lock-resets", "3"]).unwrap().lock_resets);
+    }
+
+    #[test]
+    fn parse_auto_shift() {
+        let options = parse(&[]).unwrap();
+        assert_eq!(Duration::from_millis(167), options.das);
+        assert_eq!(Duration::from_millis(33), options.arr);
+
+        let options = parse(&["--das", "100", "--arr", "0"]).unwrap();
+        assert_eq!(Duration::from_millis(100), options.das);
+        assert_eq!(Duration::ZERO, options.arr);
+    }
+
+    #[test]
+    fn parse_key_hold() {
+        assert_eq!(Duration::from_millis(600), parse(&[]).unwrap().key_hold);
+        assert_eq!(
+            Duration::from_millis(800),
+            parse(&["--key-hold", "800"]).unwrap().key_hold
+        );
+    }
+
+    #[test]
+    fn parse_clear_ticks() {
+        assert_eq!(10, parse(&[]).unwrap().clear_ticks);
+        assert_eq!(0, parse(&["--clear-ticks", "0"]).unwrap().clear_ticks);
+    }
+
+    #[test]
+    fn parse_clear_gravity() {
+        assert_eq!(LineClearGravity::Naive, parse(&[]).unwrap().clear_gravity);
+        assert_eq!(
+            LineClearGravity::Cascade,
+            parse(&["--clear-gravity", "cascade"]).unwrap().clear_gravity
+        );
+    }
+
+    #[test]
+    fn parse_keys() {
+        assert_eq!(None, parse(&[]).unwrap().keys);
+        assert_eq!(
+            Some(PathBuf::from("keys.toml")),
+            parse(&["--keys", "keys.toml"]).unwrap().keys
+        );
+    }
+
+    #[test]
+    fn help_is_not_an_invalid_argument() {
+        assert_eq!(Err(OptionsError::Help), parse(&["--help"]));
+        assert_eq!(Err(OptionsError::Help), parse(&["--seed", "1", "-h"]));
+        assert!(matches!(parse(&["--hlep"]), Err(OptionsError::Invalid(_))));
+    }
+
+    #[test]
+    fn reject_missing_and_unknown_values() {
+        assert!(parse(&["--randomizer"]).is_err());
+        assert!(parse(&["--randomizer", "tgm"]).is_err());
+        assert!(parse(&["--seed"]).is_err());
+        assert!(parse(&["--seed", "-1"]).is_err());
+        assert!(parse(&["--width", "3"]).is_err());
+        assert!(parse(&["--width", "wide"]).is_err());
+        assert!(parse(&["--height", "41"]).is_err());
+        assert!(parse(&["--height"]).is_err());
+        assert!(parse(&["--preview", "0"]).is_err());
+        assert!(parse(&["--preview", "7"]).is_err());
+        assert!(parse(&["--ghost", "maybe"]).is_err());
+        assert!(parse(&["--gravity", "tgm"]).is_err());
+        assert!(parse(&["--scoring", "tgm"]).is_err());
+        assert!(parse(&["--ultra-time"]).is_err());
+        assert!(parse(&["--ultra-time", "0"]).is_err());
+        assert!(parse(&["--lock-delay", "soon"]).is_err());
+        assert!(parse(&["--lock-resets", "-1"]).is_err());
+        assert!(parse(&["--das"]).is_err());
+        assert!(parse(&["--arr", "fast"]).is_err());
+        assert!(parse(&["--key-hold"]).is_err());
+        assert!(parse(&["--clear-ticks", "-1"]).is_err());
+        assert!(parse(&["--clear-gravity", "sticky"]).is_err());
+        assert!(parse(&["--keys"]).is_err());
+        assert!(parse(&["--speed"]).is_err());
+    }
+}
@@ -0,0 +1,78 @@
+use std::time::Duration;
+
+use anathema::{
+    component::{Children, Component, Context},
+    state::{State, Value},
+};
+
+use crate::core::scoring::TSpin;
+
+// How long a callout stays next to the arena.
+const CALLOUT_DURATION: Duration = Duration::from_millis(1500);
+
+pub(crate) struct CalloutComponent {
+    remaining: Duration,
+}
+
+impl CalloutComponent {
+    pub(crate) fn new() -> Self {
+        Self {
+            remaining: Duration::ZERO,
+        }
+    }
+}
+
+impl Component for CalloutComponent {
+    type State = CalloutState;
+    type Message = CalloutComponentMessage;
+
+    fn on_tick(
+        &mut self,
+        state: &mut Self::State,
+        _children: Children<'_, '_>,
+        _context: Context<'_, '_, Self::State>,
+        dt: Duration,
+    ) {
+        if self.remaining.is_zero() {
+            return;
+        }
+
+        self.remaining = self.remaining.saturating_sub(dt);
+        if self.remaining.is_zero() {
+            state.text.to_mut().clear();
+        }
+    }
+
+    fn on_message(
+        &mut self,
+        message: Self::Message,
+        state: &mut Self::State,
+        _children: Children<'_, '_>,
+        _context: Context<'_, '_, Self::State>,
+    ) {
+        match message {
+            CalloutComponentMessage::TSpin(t_spin, lines) => {
+                *state.text.to_mut() = t_spin.label(lines);
+                self.remaining = CALLOUT_DURATION;
+            }
+        }
+    }
+}
+
+#[derive(State)]
+pub(crate) struct CalloutState {
+    text: Value<String>,
+}
+
+impl CalloutState {
+    pub(crate) fn new() -> Self {
+        Self {
+            text: Value::new(String::new()),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub(crate) enum CalloutComponentMessage {
+    TSpin(TSpin, u16),
+}
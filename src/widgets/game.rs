@@ -6,6 +6,28 @@ use anathema::{
 };
 use anathema::component::{Children, Context};
 
+use super::game_arena::GLYPH_WIDTH;
+use crate::core::settings::Theme;
+
+// What sits around the arena in game.aml, the panels either side of it and
+// the title and line count above it.
+const PANEL_WIDTH: u16 = 20;
+const TITLE_HEIGHT: u16 = 6;
+const LINE_COUNT_HEIGHT: u16 = 3;
+const BORDER: u16 = 2;
+// The line count and level share the width above the arena, but each needs
+// room for its label and a three digit number.
+const COUNTER_MIN_WIDTH: u16 = 11;
+// Panels stacked either side of the arena, at their tallest.
+const GAME_TYPE_HEIGHT: u16 = 3;
+const TIMER_HEIGHT: u16 = 4;
+const CALLOUT_HEIGHT: u16 = 3;
+const SCORE_BOARD_HEIGHT: u16 = 6;
+const HOLD_HEIGHT: u16 = 6;
+const STREAKS_HEIGHT: u16 = 4;
+// Every next piece is up to two rows with a blank row after it.
+const PREVIEW_HEIGHT: u16 = 3;
+
 pub(crate) struct GameComponent;
 
 impl GameComponent {}
@@ -26,6 +48,7 @@ impl Component for GameComponent {
             }
             GameComponentMessage::Paused => *state.paused.to_mut() = true,
             GameComponentMessage::Running => *state.paused.to_mut() = false,
+            GameComponentMessage::Theme(theme) => state.show_theme(theme),
         }
 
         // TODO: How to pass state down to children. It would be nice to do this
@@ -36,21 +59,56 @@ impl Component for GameComponent {
 
 }
 
+// The smallest terminal the game screen fits in with an arena of this size,
+// the screen is as tall as the tallest of the three columns.
+pub(crate) fn required_terminal_size(
+    arena_width: usize,
+    arena_height: usize,
+    preview_count: usize,
+) -> (u16, u16) {
+    let arena_width = arena_width as u16 * GLYPH_WIDTH + BORDER;
+    let arena_height = arena_height as u16 + BORDER;
+    let width = 2 * PANEL_WIDTH + arena_width.max(2 * COUNTER_MIN_WIDTH);
+
+    let left = GAME_TYPE_HEIGHT + TIMER_HEIGHT + CALLOUT_HEIGHT + arena_height;
+    let middle = LINE_COUNT_HEIGHT + arena_height;
+    let next_piece = BORDER + 1 + preview_count as u16 * PREVIEW_HEIGHT;
+    let right = SCORE_BOARD_HEIGHT + next_piece + HOLD_HEIGHT + STREAKS_HEIGHT;
+    (width, TITLE_HEIGHT + left.max(middle).max(right))
+}
+
 #[derive(State)]
 pub(crate) struct GameComponentState {
     title: Value<String>,
     paused: Value<bool>,
     visible: Value<bool>,
+    theme_fg: Value<String>,
+    theme_bg: Value<String>,
+    canvas_width: Value<u16>,
+    canvas_height: Value<u16>,
+    counter_width: Value<u16>,
 }
 
 impl GameComponentState {
-    pub(crate) fn new() -> Self {
+    pub(crate) fn new(theme: Theme, arena_width: usize, arena_height: usize) -> Self {
         Self {
             title: Value::new(read_to_string("src/resources/ingame-title.txt").unwrap()),
             visible: Value::new(false),
             paused: Value::new(true),
+            theme_fg: Value::new(theme.foreground().to_string()),
+            theme_bg: Value::new(theme.background().to_string()),
+            canvas_width: Value::new(arena_width as u16 * GLYPH_WIDTH),
+            canvas_height: Value::new(arena_height as u16),
+            counter_width: Value::new(
+                (arena_width as u16 * GLYPH_WIDTH / 2 + 1).max(COUNTER_MIN_WIDTH),
+            ),
         }
     }
+
+    fn show_theme(&mut self, theme: Theme) {
+        *self.theme_fg.to_mut() = theme.foreground().to_string();
+        *self.theme_bg.to_mut() = theme.background().to_string();
+    }
 }
 
 #[derive(Debug)]
@@ -59,4 +117,5 @@ pub(crate) enum GameComponentMessage {
     Invisible,
     Paused,
     Running,
+    Theme(Theme),
 }
@@ -5,20 +5,27 @@ use anathema::{
     component::{Component, Context},
     default_widgets::Canvas,
     geometry::LocalPos,
-    state::{List, State, Value},
+    state::{Color, List, State, Value},
 };
 use anathema::component::Children;
 use smol::channel::Sender;
 
 use crate::core::{
+    auto_shift::{AutoShift, ShiftDirection},
     game_loop::{GameAction, GameLoop, MoveActionType},
+    game_mode::GameSettings,
     global_state::GlobalStateManagementMessage,
-    tetronimo::TetronimoShape,
+    held_key::HeldKey,
+    input_queue::InputQueue,
+    settings::Settings,
+    tetronimo::{RotationDirection, TetronimoShape},
 };
 
-const GLYPH_WIDTH: u16 = 2;
-const MOVE_TICK_DURATION: u64 = 100;
-const FALL_TICK_DURATION: u64 = 100;
+// Every block is drawn as a double width character.
+pub(crate) const GLYPH_WIDTH: u16 = 2;
+// A shade the style can fade, drawn twice to fill a block's width.
+const GHOST_GLYPH: char = '░';
+const SOFT_DROP_TICK_DURATION: u64 = 25;
 
 #[derive(State)]
 pub(crate) struct GameArenaComponentState {
@@ -39,10 +46,22 @@ impl From<GameArenaComponentMessage> for MoveActionType {
     fn from(value: GameArenaComponentMessage) -> Self {
         match value {
             GameArenaComponentMessage::Initialise => todo!(),
-            GameArenaComponentMessage::Rotate => MoveActionType::Rotate,
+            GameArenaComponentMessage::NewGame(_) => MoveActionType::None,
+            GameArenaComponentMessage::ApplySettings(_) => MoveActionType::None,
+            GameArenaComponentMessage::RotateClockwise => {
+                MoveActionType::Rotate(RotationDirection::Clockwise)
+            }
+            GameArenaComponentMessage::RotateCounterClockwise => {
+                MoveActionType::Rotate(RotationDirection::CounterClockwise)
+            }
+            GameArenaComponentMessage::SoftDrop => MoveActionType::SoftDrop,
+            GameArenaComponentMessage::SoftDropReleased => MoveActionType::None,
             GameArenaComponentMessage::Drop => MoveActionType::Drop,
+            GameArenaComponentMessage::Hold => MoveActionType::None,
             GameArenaComponentMessage::MoveLeft => MoveActionType::MoveLeft,
+            GameArenaComponentMessage::MoveLeftReleased => MoveActionType::None,
             GameArenaComponentMessage::MoveRight => MoveActionType::MoveRight,
+            GameArenaComponentMessage::MoveRightReleased => MoveActionType::None,
         }
     }
 }
@@ -50,19 +69,30 @@ impl From<GameArenaComponentMessage> for MoveActionType {
 #[derive(Debug, PartialEq, Eq)]
 pub(crate) enum GameArenaComponentMessage {
     Initialise,
-    Rotate,
+    NewGame(GameSettings),
+    ApplySettings(Settings),
+    RotateClockwise,
+    RotateCounterClockwise,
+    SoftDrop,
+    SoftDropReleased,
     Drop,
+    Hold,
     MoveLeft,
+    MoveLeftReleased,
     MoveRight,
+    MoveRightReleased,
 }
 
 pub(crate) struct GameArenaComponent {
     tx: Sender<GlobalStateManagementMessage>,
     last_fall_update: Duration,
-    last_move_update: Duration,
 
-    move_requested: MoveActionType,
+    input_queue: InputQueue,
+    soft_drop: HeldKey,
+    auto_shift: AutoShift,
     game_loop: GameLoop,
+    show_ghost: bool,
+    ghost_style: Style,
 }
 
 impl From<&TetronimoShape> for char {
@@ -80,14 +110,23 @@ impl From<&TetronimoShape> for char {
 }
 
 impl GameArenaComponent {
-    pub(crate) fn new(tx: Sender<GlobalStateManagementMessage>, game_loop: GameLoop) -> Self {
+    pub(crate) fn new(
+        tx: Sender<GlobalStateManagementMessage>,
+        game_loop: GameLoop,
+        show_ghost: bool,
+        auto_shift: AutoShift,
+        soft_drop: HeldKey,
+    ) -> Self {
         Self {
             tx,
             last_fall_update: Duration::ZERO,
-            last_move_update: Duration::ZERO,
 
-            move_requested: MoveActionType::None,
+            input_queue: InputQueue::new(),
+            soft_drop,
+            auto_shift,
             game_loop,
+            show_ghost,
+            ghost_style: ghost_style(),
         }
     }
 
@@ -98,6 +137,17 @@ impl GameArenaComponent {
         });
     }
 
+    // The ghost is drawn dimmed so it is easy to tell apart from the piece.
+    fn draw_ghost(&self, canvas: &mut Canvas) {
+        self.game_loop.draw_ghost(|_, position| {
+            let x = position.x * GLYPH_WIDTH;
+            for offset in 0..GLYPH_WIDTH {
+                let position = LocalPos::new(x + offset, position.y);
+                canvas.put(GHOST_GLYPH, self.ghost_style, position)
+            }
+        });
+    }
+
     fn draw_arena(&self, canvas: &mut Canvas) {
         self.game_loop
             .draw_arena(|character, position| match character {
@@ -118,65 +168,86 @@ impl GameArenaComponent {
         mut children: Children<'_, '_>,
         dt: Duration,
     ) {
+        self.game_loop.advance_time(dt);
+        self.game_loop.clear_tick();
         self.last_fall_update += dt;
-        self.last_move_update += dt;
-
-        if self.last_move_update >= Duration::from_millis(MOVE_TICK_DURATION) {
-            self.last_move_update = Duration::ZERO;
-            match self.move_requested {
-                MoveActionType::MoveLeft => self
-                    .game_loop
-                    .handle_input(GameAction::Move(MoveActionType::MoveLeft)),
-                MoveActionType::MoveRight => self
-                    .game_loop
-                    .handle_input(GameAction::Move(MoveActionType::MoveRight)),
-                MoveActionType::Rotate => self
-                    .game_loop
-                    .handle_input(GameAction::Move(MoveActionType::Rotate)),
-                _ => (),
+        self.soft_drop.tick(dt);
+
+        // Everything pressed since the last tick, in the order it was pressed.
+        // Presses wait in the queue while a locked piece is being cleared away.
+        let tx = &self.tx;
+        self.input_queue
+            .drain(&mut self.game_loop, |game_loop| run_state_machine(game_loop, tx));
+
+        // Every column is its own move so the lock delay sees each one, even
+        // when several happen in the same tick.
+        let shifts = self.auto_shift.tick(dt);
+        if let Some(direction) = self.auto_shift.direction() {
+            for _ in 0..shifts.min(self.game_loop.arena_width()) {
+                self.game_loop.handle_input(GameAction::Move(direction.into()));
+                run_state_machine(&mut self.game_loop, &self.tx);
             }
-            self.move_requested = MoveActionType::None;
         }
 
-        if self.last_fall_update >= Duration::from_millis(FALL_TICK_DURATION) {
-            self.last_fall_update = Duration::ZERO;
-            self.game_loop.fall_tick();
-        }
+        // Soft drop starts with the first press rather than waiting for the
+        // terminal to repeat it.
+        let soft_dropping = self.soft_drop.is_held();
+        let fall_interval = self.game_loop.fall_interval();
+        let fall_tick_duration = match soft_dropping {
+            true => fall_interval.min(Duration::from_millis(SOFT_DROP_TICK_DURATION)),
+            false => fall_interval,
+        };
 
-        self.game_loop.do_state_machine(
-            |score| {
-                let _ = self
-                    .tx
-                    .try_send(GlobalStateManagementMessage::UpdateScore(score));
-            },
-            |score| {
-                let _ = self
-                    .tx
-                    .try_send(GlobalStateManagementMessage::UpdateLines(score));
-            },
-            |shape| {
-                let _ = self
-                    .tx
-                    .try_send(GlobalStateManagementMessage::UpdateNextTetronimo(shape));
-            },
-            |statistics| {
-                let _ = self
-                    .tx
-                    .try_send(GlobalStateManagementMessage::UpdateStatistics(
-                        statistics.into(),
-                    ));
-            },
+        let tx = &self.tx;
+        self.last_fall_update = self.game_loop.fall(
+            self.last_fall_update,
+            fall_tick_duration,
+            soft_dropping,
+            |game_loop| run_state_machine(game_loop, tx),
         );
 
+        run_state_machine(&mut self.game_loop, &self.tx);
+
         children.elements().by_tag("canvas").first(|el, _| {
             let canvas = el.to::<Canvas>();
 
             self.draw_arena(canvas);
-            self.draw_tetronimo(canvas);
+            // The piece has already locked into the rows being wiped.
+            if !self.game_loop.is_clearing_rows() {
+                if self.show_ghost {
+                    self.draw_ghost(canvas);
+                }
+                self.draw_tetronimo(canvas);
+            }
         });
     }
 }
 
+fn ghost_style() -> Style {
+    let mut style = Style::new();
+    style.set_fg(Color::DarkGrey);
+    style
+}
+
+fn run_state_machine(game_loop: &mut GameLoop, tx: &Sender<GlobalStateManagementMessage>) {
+    game_loop.do_state_machine(
+        |score| {
+            let _ = tx.try_send(GlobalStateManagementMessage::UpdateScore(score));
+        },
+        |score| {
+            let _ = tx.try_send(GlobalStateManagementMessage::UpdateLines(score));
+        },
+        |shapes| {
+            let _ = tx.try_send(GlobalStateManagementMessage::UpdateNextTetronimoQueue(shapes));
+        },
+        |statistics| {
+            let _ = tx.try_send(GlobalStateManagementMessage::UpdateStatistics(
+                statistics.into(),
+            ));
+        },
+    );
+}
+
 impl Component for GameArenaComponent {
     type State = GameArenaComponentState;
     type Message = GameArenaComponentMessage;
@@ -191,7 +262,13 @@ impl Component for GameArenaComponent {
         let is_paused = context.attributes.get_as::<bool>("paused");
 
         match is_paused {
-            Some(true) => self.game_loop.handle_input(GameAction::Pause),
+            // Keys released while paused never reach the arena.
+            Some(true) => {
+                self.auto_shift.release_all();
+                self.soft_drop.reset();
+                self.input_queue.clear();
+                self.game_loop.handle_input(GameAction::Pause)
+            }
             _ => self.handle_moving_state(state, children, dt),
         }
     }
@@ -205,8 +282,39 @@ impl Component for GameArenaComponent {
     ) {
         if message == GameArenaComponentMessage::Initialise {
             self.game_loop.initialise();
-        } else if self.move_requested == MoveActionType::None {
-            self.move_requested = message.into();
+        } else if let GameArenaComponentMessage::NewGame(settings) = message {
+            self.auto_shift.release_all();
+            self.soft_drop.reset();
+            self.input_queue.clear();
+            self.game_loop.apply_settings(settings);
+            self.game_loop.initialise();
+        } else if let GameArenaComponentMessage::ApplySettings(settings) = message {
+            self.show_ghost = settings.ghost;
+            self.auto_shift.set_timing(settings.das, settings.arr);
+            self.game_loop.set_preview_count(settings.preview);
+        } else if message == GameArenaComponentMessage::SoftDrop {
+            self.soft_drop.press();
+        } else if message == GameArenaComponentMessage::SoftDropReleased {
+            self.soft_drop.release();
+        } else if message == GameArenaComponentMessage::Hold {
+            self.input_queue.push(GameAction::Hold);
+        } else if message == GameArenaComponentMessage::MoveLeft {
+            if self.auto_shift.press(ShiftDirection::Left) {
+                self.input_queue.push(GameAction::Move(MoveActionType::MoveLeft));
+            }
+        } else if message == GameArenaComponentMessage::MoveLeftReleased {
+            self.auto_shift.release(ShiftDirection::Left);
+        } else if message == GameArenaComponentMessage::MoveRight {
+            if self.auto_shift.press(ShiftDirection::Right) {
+                self.input_queue.push(GameAction::Move(MoveActionType::MoveRight));
+            }
+        } else if message == GameArenaComponentMessage::MoveRightReleased {
+            self.auto_shift.release(ShiftDirection::Right);
+        } else {
+            let action: MoveActionType = message.into();
+            if action != MoveActionType::None {
+                self.input_queue.push(GameAction::Move(action));
+            }
         }
     }
 }
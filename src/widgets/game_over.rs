@@ -2,12 +2,18 @@ use std::{fs::read_to_string, time::Duration};
 
 use anathema::{
     component::Component,
-    state::{State, Value},
+    state::{List, State, Value},
 };
 use anathema::component::{Children, Context};
 use smol::channel::Sender;
 
-use crate::core::global_state::GlobalStateManagementMessage;
+use crate::{
+    core::{
+        game_loop::SPLIT_LINES, global_state::GlobalStateManagementMessage,
+        high_scores::MAX_INITIALS,
+    },
+    widgets::timer::format_time,
+};
 
 pub(crate) struct GameOverComponent {
     duration: Duration,
@@ -21,6 +27,48 @@ impl GameOverComponent {
             tx,
         }
     }
+
+    fn show_result(
+        &mut self,
+        state: &mut GameOverComponentState,
+        label: &str,
+        value: String,
+        splits: Vec<String>,
+        personal_best: bool,
+    ) {
+        *state.result_label.to_mut() = label.to_string();
+        *state.result_value.to_mut() = value;
+        *state.personal_best.to_mut() = personal_best;
+        for _ in 0..state.splits.len() {
+            state.splits.pop_front();
+        }
+        for split in splits {
+            state.splits.push_back(split);
+        }
+    }
+
+    fn handle_name_entry(&mut self, action: NameEntryAction, state: &mut GameOverComponentState) {
+        match action {
+            NameEntryAction::Char(character) => {
+                let mut initials = state.initials.to_mut();
+                if character.is_ascii_alphanumeric() && initials.len() < MAX_INITIALS {
+                    initials.push(character.to_ascii_uppercase());
+                }
+            }
+            NameEntryAction::Backspace => {
+                state.initials.to_mut().pop();
+            }
+            NameEntryAction::Confirm => {
+                let initials = state.initials.to_ref().to_string();
+                let _ = self
+                    .tx
+                    .try_send(GlobalStateManagementMessage::SubmitInitials(initials));
+                // The screen stays up for the usual time once the name is in.
+                self.duration = Duration::ZERO;
+                *state.entering_initials.to_mut() = false
+            }
+        }
+    }
 }
 
 impl Component for GameOverComponent {
@@ -34,7 +82,7 @@ impl Component for GameOverComponent {
         _children: Children<'_, '_>,
         _context: Context<'_, '_, Self::State>,
         dt: Duration) {
-        if state.visible.copy_value() {
+        if state.visible.copy_value() && !state.entering_initials.copy_value() {
             self.duration += dt;
             if self.duration > Duration::new(5, 0) {
                 let _ = self.tx.try_send(GlobalStateManagementMessage::MainMenu);
@@ -51,9 +99,38 @@ impl Component for GameOverComponent {
         match message {
             GameOverComponentMessage::Visible => {
                 self.duration = Duration::ZERO;
+                *state.won.to_mut() = false;
+                *state.save_error.to_mut() = String::new();
                 *state.visible.to_mut() = true
             }
+            GameOverComponentMessage::Won => *state.won.to_mut() = true,
             GameOverComponentMessage::Invisible => *state.visible.to_mut() = false,
+            GameOverComponentMessage::Seed(seed) => *state.seed.to_mut() = seed,
+            GameOverComponentMessage::Score(score) => {
+                self.show_result(state, "SCORE", score.to_string(), vec![], false)
+            }
+            GameOverComponentMessage::Time(time, splits, personal_best) => {
+                let splits = splits
+                    .iter()
+                    .zip(1..)
+                    .map(|(split, index)| {
+                        format!("{:>3} LINES {}", index * SPLIT_LINES, format_time(*split))
+                    })
+                    .collect();
+                self.show_result(state, "TIME", format_time(time), splits, personal_best)
+            }
+            GameOverComponentMessage::EnterInitials => {
+                state.initials.to_mut().clear();
+                *state.entering_initials.to_mut() = true
+            }
+            GameOverComponentMessage::NameEntry(action) => {
+                if state.entering_initials.copy_value() {
+                    self.handle_name_entry(action, state)
+                }
+            }
+            GameOverComponentMessage::SaveFailed(error) => {
+                *state.save_error.to_mut() = format!("High scores not saved: {error}")
+            }
         }
     }
 }
@@ -62,6 +139,15 @@ impl Component for GameOverComponent {
 pub(crate) struct GameOverComponentState {
     visible: Value<bool>,
     title: Value<String>,
+    won: Value<bool>,
+    result_label: Value<String>,
+    result_value: Value<String>,
+    splits: Value<List<String>>,
+    personal_best: Value<bool>,
+    seed: Value<u64>,
+    entering_initials: Value<bool>,
+    initials: Value<String>,
+    save_error: Value<String>,
 }
 
 impl GameOverComponentState {
@@ -69,6 +155,15 @@ impl GameOverComponentState {
         Self {
             visible: Value::new(false),
             title: Value::new(read_to_string("src/resources/game-over.txt").unwrap()),
+            won: Value::new(false),
+            result_label: Value::new(String::new()),
+            result_value: Value::new(String::new()),
+            splits: List::empty().into(),
+            personal_best: Value::new(false),
+            seed: Value::new(0),
+            entering_initials: Value::new(false),
+            initials: Value::new(String::new()),
+            save_error: Value::new(String::new()),
         }
     }
 }
@@ -76,4 +171,18 @@ impl GameOverComponentState {
 pub(crate) enum GameOverComponentMessage {
     Visible,
     Invisible,
+    Won,
+    Seed(u64),
+    Score(u32),
+    // Final time, the time at every split and whether it is a new best.
+    Time(Duration, Vec<Duration>, bool),
+    EnterInitials,
+    NameEntry(NameEntryAction),
+    SaveFailed(String),
+}
+
+pub(crate) enum NameEntryAction {
+    Char(char),
+    Backspace,
+    Confirm,
 }
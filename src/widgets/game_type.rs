@@ -1,4 +1,9 @@
-use anathema::{component::Component, state::State};
+use anathema::{
+    component::Component,
+    state::{State, Value},
+};
+
+use crate::core::game_mode::GameMode;
 
 pub(crate) struct GameTypeComponent;
 
@@ -7,9 +12,34 @@ impl GameTypeComponent {}
 impl Component for GameTypeComponent {
     type State = GameTypeState;
     type Message = GameTypeMessage;
+
+    fn on_message(
+        &mut self,
+        message: Self::Message,
+        state: &mut Self::State,
+        _children: anathema::component::Children<'_, '_>,
+        _context: anathema::component::Context<'_, '_, Self::State>,
+    ) {
+        match message {
+            GameTypeMessage::Mode(mode) => *state.game_type.to_mut() = mode.label().to_string(),
+        }
+    }
 }
 
 #[derive(State)]
-pub(crate) struct GameTypeState {}
+pub(crate) struct GameTypeState {
+    game_type: Value<String>,
+}
 
-pub(crate) struct GameTypeMessage {}
+impl GameTypeState {
+    pub(crate) fn new() -> Self {
+        Self {
+            game_type: Value::new(GameMode::default().label().to_string()),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub(crate) enum GameTypeMessage {
+    Mode(GameMode),
+}
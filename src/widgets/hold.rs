@@ -0,0 +1,55 @@
+use crate::core::tetronimo::TetronimoShape;
+use anathema::{
+    component::Component,
+    state::{State, Value},
+};
+
+pub(crate) struct HoldComponent;
+
+impl HoldComponent {}
+
+impl Component for HoldComponent {
+    type State = HoldState;
+    type Message = HoldComponentMessage;
+
+    fn on_message(
+        &mut self,
+        message: Self::Message,
+        state: &mut Self::State,
+        _children: anathema::component::Children<'_, '_>,
+        _context: anathema::component::Context<'_, '_, Self::State>,
+    ) {
+        *state.shape.to_mut() = match message.shape {
+            Some(shape) => shape.into(),
+            None => String::new(),
+        };
+        *state.locked.to_mut() = message.locked;
+    }
+}
+
+#[derive(State)]
+pub(crate) struct HoldState {
+    shape: Value<String>,
+    locked: Value<bool>,
+}
+
+impl HoldState {
+    pub(crate) fn new() -> Self {
+        Self {
+            shape: Value::new(String::new()),
+            locked: Value::new(false),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct HoldComponentMessage {
+    shape: Option<TetronimoShape>,
+    locked: bool,
+}
+
+impl HoldComponentMessage {
+    pub(crate) fn new(shape: Option<TetronimoShape>, locked: bool) -> Self {
+        Self { shape, locked }
+    }
+}
@@ -0,0 +1,43 @@
+use anathema::{
+    component::Component,
+    state::{State, Value},
+};
+
+pub(crate) struct LevelComponent {}
+
+impl LevelComponent {}
+
+impl Component for LevelComponent {
+    type State = LevelState;
+    type Message = LevelComponentMessage;
+
+    fn on_message(
+        &mut self,
+        message: Self::Message,
+        state: &mut Self::State,
+        _children: anathema::component::Children<'_, '_>,
+        _context: anathema::component::Context<'_, '_, Self::State>,
+    ) {
+        match message {
+            LevelComponentMessage::Level(value) => *state.level.to_mut() = value,
+        }
+    }
+}
+
+#[derive(State)]
+pub(crate) struct LevelState {
+    level: Value<u16>,
+}
+
+impl LevelState {
+    pub(crate) fn new() -> Self {
+        Self {
+            level: Value::new(0),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub(crate) enum LevelComponentMessage {
+    Level(u16),
+}
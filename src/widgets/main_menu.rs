@@ -7,37 +7,132 @@ use anathema::{
 use anathema::component::{Children, Context};
 use smol::channel::Sender;
 
-use crate::core::global_state::GlobalStateManagementMessage;
+use crate::core::{
+    game_mode::GameMode,
+    global_state::GlobalStateManagementMessage,
+    menu::Menu,
+    settings::{Setting, Settings},
+};
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum MainMenuItem {
+    Start,
+    Settings,
+    Exit,
+}
+
+const MAIN_MENU_ITEMS: [MainMenuItem; 3] =
+    [MainMenuItem::Start, MainMenuItem::Settings, MainMenuItem::Exit];
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum SettingsItem {
+    Change(Setting),
+    Back,
+}
 
+// Rows on the settings screen in the order they are shown, the height row is
+// only there for B-Type games.
+const SETTINGS_ITEMS: [SettingsItem; 9] = [
+    SettingsItem::Change(Setting::StartLevel),
+    SettingsItem::Change(Setting::Mode),
+    SettingsItem::Change(Setting::GarbageHeight),
+    SettingsItem::Change(Setting::Ghost),
+    SettingsItem::Change(Setting::Das),
+    SettingsItem::Change(Setting::Arr),
+    SettingsItem::Change(Setting::Preview),
+    SettingsItem::Change(Setting::Theme),
+    SettingsItem::Back,
+];
+
+// The settings in play can include overrides from the command line, only
+// what is changed on the settings screen makes it into the saved settings.
 pub(crate) struct MainMenuComponent {
     tx: Sender<GlobalStateManagementMessage>,
+    settings: Settings,
+    saved_settings: Settings,
+    main_menu: Menu<MainMenuItem>,
+    settings_menu: Menu<SettingsItem>,
 }
 
 impl MainMenuComponent {
-    pub(crate) fn new(tx: Sender<GlobalStateManagementMessage>) -> Self {
-        Self { tx }
+    pub(crate) fn new(
+        tx: Sender<GlobalStateManagementMessage>,
+        settings: Settings,
+        saved_settings: Settings,
+    ) -> Self {
+        Self {
+            tx,
+            settings,
+            saved_settings,
+            main_menu: Menu::new(&MAIN_MENU_ITEMS),
+            settings_menu: Menu::new(&SETTINGS_ITEMS),
+        }
     }
 
     fn handle_selection(&mut self, state: &mut MainMenuComponentState, selection: MainMenuAction) {
+        match state.settings_open.copy_value() {
+            true => self.handle_settings(state, selection),
+            false => self.handle_main_menu(state, selection),
+        }
+    }
+
+    fn handle_main_menu(&mut self, state: &mut MainMenuComponentState, selection: MainMenuAction) {
         match selection {
-            MainMenuAction::Up => self.toggle_menu(state),
-            MainMenuAction::Down => self.toggle_menu(state),
-            MainMenuAction::Enter => match state.start_highlighted.copy_value() {
-                true => {
+            MainMenuAction::Up => self.main_menu.move_selection(-1, |_| true),
+            MainMenuAction::Down => self.main_menu.move_selection(1, |_| true),
+            MainMenuAction::Left | MainMenuAction::Right => (),
+            MainMenuAction::Enter => match self.main_menu.selected() {
+                MainMenuItem::Start => {
+                    let _ = self.tx.try_send(GlobalStateManagementMessage::NewGame(
+                        self.settings.game_settings(),
+                    ));
                     let _ = self.tx.try_send(GlobalStateManagementMessage::Playing);
                 }
-                false => {
+                MainMenuItem::Settings => {
+                    self.settings_menu.reset();
+                    *state.setting_selected.to_mut() = 0;
+                    *state.settings_open.to_mut() = true;
+                }
+                MainMenuItem::Exit => {
                     let _ = self.tx.try_send(GlobalStateManagementMessage::Exit);
                 }
             },
         }
+        *state.selected.to_mut() = self.main_menu.index() as u16;
     }
 
-    fn toggle_menu(&mut self, state: &mut MainMenuComponentState) {
-        match state.start_highlighted.copy_value() {
-            true => *state.start_highlighted.to_mut() = false,
-            false => *state.start_highlighted.to_mut() = true,
-        };
+    fn handle_settings(&mut self, state: &mut MainMenuComponentState, selection: MainMenuAction) {
+        let b_type = self.settings.mode == GameMode::BType;
+        let shown =
+            |item: SettingsItem| b_type || item != SettingsItem::Change(Setting::GarbageHeight);
+
+        match (selection, self.settings_menu.selected()) {
+            (MainMenuAction::Up, _) => self.settings_menu.move_selection(-1, shown),
+            (MainMenuAction::Down, _) => self.settings_menu.move_selection(1, shown),
+            (MainMenuAction::Enter, SettingsItem::Back) => {
+                *state.settings_open.to_mut() = false;
+            }
+            (MainMenuAction::Left, SettingsItem::Change(setting)) => {
+                self.change_setting(state, setting, -1)
+            }
+            (MainMenuAction::Right | MainMenuAction::Enter, SettingsItem::Change(setting)) => {
+                self.change_setting(state, setting, 1)
+            }
+            _ => (),
+        }
+        *state.setting_selected.to_mut() = self.settings_menu.index() as u16;
+    }
+
+    // Every change is saved and applied straight away.
+    fn change_setting(&mut self, state: &mut MainMenuComponentState, setting: Setting, change: i32) {
+        self.settings.adjust(setting, change);
+        self.saved_settings.copy_setting(setting, &self.settings);
+        state.show_settings(&self.settings);
+        *state.save_error.to_mut() = String::new();
+        let _ = self.tx.try_send(GlobalStateManagementMessage::UpdateSettings(
+            self.settings,
+            self.saved_settings,
+        ));
     }
 }
 
@@ -56,6 +151,9 @@ impl Component for MainMenuComponent {
             MainMenuComponentMessage::Visible => *state.visible.to_mut() = true,
             MainMenuComponentMessage::Invisible => *state.visible.to_mut() = false,
             MainMenuComponentMessage::Change(selection) => self.handle_selection(state, selection),
+            MainMenuComponentMessage::SaveFailed(error) => {
+                *state.save_error.to_mut() = format!("Settings not saved: {error}")
+            }
         }
     }
 
@@ -65,6 +163,8 @@ impl Component for MainMenuComponent {
 pub(crate) enum MainMenuAction {
     Up,
     Down,
+    Left,
+    Right,
     Enter,
 }
 
@@ -73,21 +173,76 @@ pub(crate) enum MainMenuComponentMessage {
     Visible,
     Invisible,
     Change(MainMenuAction),
+    SaveFailed(String),
 }
 
 #[derive(State)]
 pub(crate) struct MainMenuComponentState {
     title: Value<String>,
-    start_highlighted: Value<bool>,
+    selected: Value<u16>,
+    summary: Value<String>,
+    settings_open: Value<bool>,
+    setting_selected: Value<u16>,
+    start_level: Value<u16>,
+    game_type: Value<String>,
+    b_type: Value<bool>,
+    garbage_height: Value<u16>,
+    ghost: Value<String>,
+    das: Value<u16>,
+    arr: Value<u16>,
+    preview: Value<u16>,
+    theme: Value<String>,
+    theme_fg: Value<String>,
+    theme_bg: Value<String>,
+    save_error: Value<String>,
     visible: Value<bool>,
 }
 
 impl MainMenuComponentState {
-    pub(crate) fn new() -> Self {
-        Self {
+    pub(crate) fn new(settings: &Settings) -> Self {
+        let mut state = Self {
             title: Value::new(read_to_string("src/resources/title.txt").unwrap()),
-            start_highlighted: Value::new(true),
+            selected: Value::new(0),
+            summary: Value::new(String::new()),
+            settings_open: Value::new(false),
+            setting_selected: Value::new(0),
+            start_level: Value::new(0),
+            game_type: Value::new(String::new()),
+            b_type: Value::new(false),
+            garbage_height: Value::new(0),
+            ghost: Value::new(String::new()),
+            das: Value::new(0),
+            arr: Value::new(0),
+            preview: Value::new(0),
+            theme: Value::new(String::new()),
+            theme_fg: Value::new(String::new()),
+            theme_bg: Value::new(String::new()),
+            save_error: Value::new(String::new()),
             visible: Value::new(true),
-        }
+        };
+        state.show_settings(settings);
+        state
+    }
+
+    fn show_settings(&mut self, settings: &Settings) {
+        *self.summary.to_mut() = format!(
+            "{}  LEVEL {}",
+            settings.mode.label(),
+            settings.start_level
+        );
+        *self.start_level.to_mut() = settings.start_level;
+        *self.game_type.to_mut() = settings.mode.label().to_string();
+        *self.b_type.to_mut() = settings.mode == GameMode::BType;
+        *self.garbage_height.to_mut() = settings.garbage_height;
+        *self.ghost.to_mut() = match settings.ghost {
+            true => "ON".to_string(),
+            false => "OFF".to_string(),
+        };
+        *self.das.to_mut() = settings.das.as_millis() as u16;
+        *self.arr.to_mut() = settings.arr.as_millis() as u16;
+        *self.preview.to_mut() = settings.preview as u16;
+        *self.theme.to_mut() = settings.theme.label().to_string();
+        *self.theme_fg.to_mut() = settings.theme.foreground().to_string();
+        *self.theme_bg.to_mut() = settings.theme.background().to_string();
     }
 }
@@ -1,7 +1,10 @@
+pub(crate) mod callout;
 pub(crate) mod game;
 pub(crate) mod game_arena;
 pub(crate) mod game_over;
 pub(crate) mod game_type;
+pub(crate) mod hold;
+pub(crate) mod level;
 pub(crate) mod line_count;
 pub(crate) mod main_menu;
 pub(crate) mod next_piece;
@@ -9,3 +12,5 @@ pub(crate) mod scoreboard;
 pub(crate) mod static_piece;
 pub(crate) mod statistic;
 pub(crate) mod statistics;
+pub(crate) mod streaks;
+pub(crate) mod timer;
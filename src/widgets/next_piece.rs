@@ -1,7 +1,7 @@
 use crate::core::tetronimo::TetronimoShape;
 use anathema::{
     component::Component,
-    state::{State, Value},
+    state::{List, State, Value},
 };
 
 pub(crate) struct NextPieceComponent;
@@ -19,7 +19,12 @@ impl Component for NextPieceComponent {
         _children: anathema::component::Children<'_, '_>,
         _context: anathema::component::Context<'_, '_, Self::State>,
     ) {
-        *state.shape.to_mut() = message.shape.into();
+        for _ in 0..state.shapes.len() {
+            state.shapes.pop_front();
+        }
+        for shape in message.shapes {
+            state.shapes.push_back(shape.into());
+        }
     }
 }
 
@@ -39,24 +44,24 @@ impl From<TetronimoShape> for String {
 
 #[derive(State)]
 pub(crate) struct NextPieceState {
-    shape: Value<String>,
+    shapes: Value<List<String>>,
 }
 
 impl NextPieceState {
     pub(crate) fn new() -> Self {
         Self {
-            shape: Value::new('I'.to_string()),
+            shapes: List::empty().into(),
         }
     }
 }
 
 #[derive(Debug)]
 pub(crate) struct NextPieceComponentMessage {
-    shape: TetronimoShape,
+    shapes: Vec<TetronimoShape>,
 }
 
 impl NextPieceComponentMessage {
-    pub(crate) fn new(shape: TetronimoShape) -> Self {
-        Self { shape }
+    pub(crate) fn new(shapes: Vec<TetronimoShape>) -> Self {
+        Self { shapes }
     }
 }
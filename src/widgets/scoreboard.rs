@@ -20,23 +20,27 @@ impl Component for ScoreBoardComponent {
     ) {
         match message {
             ScoreBoardComponentMessage::Score(value) => *state.current_score.to_mut() = value,
+            ScoreBoardComponentMessage::Top(value) => *state.top_score.to_mut() = value,
         }
     }
 }
 
 #[derive(State)]
 pub(crate) struct ScoreBoardComponentState {
-    current_score: Value<u16>,
+    top_score: Value<u32>,
+    current_score: Value<u32>,
 }
 
 impl ScoreBoardComponentState {
     pub(crate) fn new() -> Self {
         Self {
+            top_score: Value::new(0),
             current_score: Value::new(0),
         }
     }
 }
 
 pub(crate) enum ScoreBoardComponentMessage {
-    Score(u16),
+    Score(u32),
+    Top(u32),
 }
@@ -0,0 +1,47 @@
+use anathema::{
+    component::Component,
+    state::{State, Value},
+};
+
+pub(crate) struct StreaksComponent {}
+
+impl StreaksComponent {}
+
+impl Component for StreaksComponent {
+    type State = StreaksState;
+    type Message = StreaksComponentMessage;
+
+    fn on_message(
+        &mut self,
+        message: Self::Message,
+        state: &mut Self::State,
+        _children: anathema::component::Children<'_, '_>,
+        _context: anathema::component::Context<'_, '_, Self::State>,
+    ) {
+        match message {
+            StreaksComponentMessage::Combo(value) => *state.combo.to_mut() = value,
+            StreaksComponentMessage::BackToBack(value) => *state.back_to_back.to_mut() = value,
+        }
+    }
+}
+
+#[derive(State)]
+pub(crate) struct StreaksState {
+    combo: Value<u16>,
+    back_to_back: Value<u16>,
+}
+
+impl StreaksState {
+    pub(crate) fn new() -> Self {
+        Self {
+            combo: Value::new(0),
+            back_to_back: Value::new(0),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub(crate) enum StreaksComponentMessage {
+    Combo(u16),
+    BackToBack(u16),
+}
@@ -0,0 +1,70 @@
+use std::time::Duration;
+
+use anathema::{
+    component::Component,
+    state::{State, Value},
+};
+
+pub(crate) struct TimerComponent;
+
+impl TimerComponent {}
+
+impl Component for TimerComponent {
+    type State = TimerState;
+    type Message = TimerComponentMessage;
+
+    fn on_message(
+        &mut self,
+        message: Self::Message,
+        state: &mut Self::State,
+        _children: anathema::component::Children<'_, '_>,
+        _context: anathema::component::Context<'_, '_, Self::State>,
+    ) {
+        match message {
+            TimerComponentMessage::Show(visible) => {
+                *state.time.to_mut() = format_time(Duration::ZERO);
+                *state.visible.to_mut() = visible
+            }
+            TimerComponentMessage::Time(time) => *state.time.to_mut() = format_time(time),
+            // Modes without a best time leave it out altogether.
+            TimerComponentMessage::Best(best) => {
+                *state.best.to_mut() = best.map(format_time).unwrap_or_default()
+            }
+        }
+    }
+}
+
+// Minutes, seconds and milliseconds, e.g. 1:02.345
+pub(crate) fn format_time(time: Duration) -> String {
+    let seconds = time.as_secs();
+    format!(
+        "{}:{:02}.{:03}",
+        seconds / 60,
+        seconds % 60,
+        time.subsec_millis()
+    )
+}
+
+#[derive(State)]
+pub(crate) struct TimerState {
+    visible: Value<bool>,
+    time: Value<String>,
+    best: Value<String>,
+}
+
+impl TimerState {
+    pub(crate) fn new() -> Self {
+        Self {
+            visible: Value::new(false),
+            time: Value::new(format_time(Duration::ZERO)),
+            best: Value::new(String::new()),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub(crate) enum TimerComponentMessage {
+    Show(bool),
+    Time(Duration),
+    Best(Option<Duration>),
+}